@@ -0,0 +1,32 @@
+//! Times `athing space` counting what a rule accepts over the full 3x3 space,
+//! 19^9 patterns, for rules that tell apart one to four kinds of pieces.
+//!
+//! Run with `cargo bench --bench space`.
+
+use std::process::Command;
+use std::time::Instant;
+
+const RULES: &[&str] = &[
+    "there is a red piece",
+    "every red piece is large",
+    "a red piece is in the top row",
+    "more large red pieces than small blue pieces",
+    "no small piece touches a blue piece",
+    "a large red piece touches a small blue piece",
+];
+
+fn main() {
+    for rule in RULES {
+        let start = Instant::now();
+        let output = Command::new(env!("CARGO_BIN_EXE_athing"))
+            .arg("space")
+            .args(rule.split(' '))
+            .output()
+            .expect("athing runs");
+        let elapsed = start.elapsed();
+        assert!(output.status.success(), "{rule} failed");
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let counted = stdout.lines().last().unwrap_or_default();
+        println!("{:>8.2}s  {counted}", elapsed.as_secs_f64());
+    }
+}
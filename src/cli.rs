@@ -0,0 +1,356 @@
+use std::time::{Duration, Instant};
+
+use color_eyre::{Result, eyre::eyre};
+use ratatui::style::Color;
+
+use crate::{
+    App, COLOR_BLUE, COLOR_RED, COLOR_YELLOW, Confirmation, Datos, Keymap, Pattern, PatternParent,
+    PyramidType, SymbolSize,
+    formats::{
+        game_file::{gallery_to_game_file, parse_game_file},
+        notation::parse_parent,
+        svg::gallery_to_svg,
+        text::{TextStyle, gallery_to_text},
+    },
+    game::guesser::Strategy,
+    helpers::fuzz::{fuzz, fuzz_gallery},
+    helpers::gallery::select_last_pattern,
+    helpers::game_handler::start_game,
+    helpers::rng::Rng,
+    solver::{
+        difficulty::estimate,
+        rule::Rule,
+        space::{AttributeSet, canonical, count_accepted, decode, encode},
+    },
+};
+
+const USAGE: &str =
+    "usage: athing [--competitive] [--bot-master] [--bots STRATEGY,...] [--think-ms MS]
+              [--load GAME.zendo] [--confirm ACTION,...] [--keys PRESET]
+       athing svg OUT.svg [PATTERN...]
+       athing dump [--ansi] [-o OUT] [PATTERN...]
+       athing difficulty RULE
+       athing space [--pieces LETTERS] [--symmetric] [--limit N] [RULE]
+       athing fuzz [STEPS] [SEED]
+       athing fuzz-gallery [STEPS] [SEED]
+
+--competitive turns off the rule assistant, like :assistant off in the app.
+  During a game only the master can turn it on or off.
+--bot-master starts a game against a computer master.
+--bots adds computer guessers: random, greedy or conservative. Without
+  --bot-master you are the master.
+--think-ms is how long bots wait between moves, 600 by default.
+--load opens a saved gallery with its notes, Ctrl-s in the app saves it
+  again. Without it Ctrl-s writes zendui-game.zendo.
+--confirm sets which actions ask first: quit (with unsaved koans),
+  overwrite, delete, reveal (the rule list during a game) and new-game (over
+  a running one or unsaved koans). All of them by default, \"none\" for
+  none.
+--keys picks the key preset, default or vim. The vim keys move with hjkl
+  and take counts, dd, yy and p remove, yank and put pieces, v selects a
+  block and Y yanks the whole koan, u and Ctrl-r
+  undo and redo piece changes, and :w and :q save and quit.
+
+PATTERN is written in pattern notation, e.g. \"+ Lr^ . Sb/ / . . Mya\".
+Without patterns, svg draws a legend of every piece and dump reads a game
+file from stdin, like Ctrl-s saves it, or just one pattern per line.
+
+RULE is written like the rule assistant shows it, e.g. \"every red piece is
+large\" or \"no small piece touches a blue piece\".
+
+space counts the patterns RULE accepts among every pattern built from the
+pieces LETTERS allows, in notation letters, e.g. \"rbS^\" for small straight
+red and blue pieces. Without RULE, or with --symmetric to also count the
+patterns that differ up to rotation and reflection, it scans the patterns
+one by one and reports how fast it went. The scan stops after N patterns,
+100000000 by default, and estimates the time for the rest.
+
+fuzz presses STEPS random keys, 10000 by default, and draws the app after
+each one, reporting the keys that led to a panic or to a selection the
+gallery does not show. Run it from a debug build, release builds do not
+check for overflow. fuzz-gallery does the same with up to a hundred random
+koans and only the keys that move around the gallery. Odd fuzz seeds use
+the vim keys.";
+
+/// Runs a command line subcommand, or returns the app to start the TUI with.
+pub fn run(args: &[String]) -> Result<Option<App>> {
+    match args.first().map(String::as_str) {
+        None => Ok(Some(App::new())),
+        Some(flag) if flag.starts_with("--") && flag != "--help" => tui_options(args).map(Some),
+        Some("svg") => {
+            let patterns = parse_patterns(args.get(2..).unwrap_or_default())?;
+            let patterns = if patterns.is_empty() {
+                legend()
+            } else {
+                patterns
+            };
+            let svg = gallery_to_svg(&patterns, 2);
+            write_output(args.get(1), &svg)?;
+            Ok(None)
+        }
+        Some("dump") => {
+            dump(&args[1..])?;
+            Ok(None)
+        }
+        Some("difficulty") => {
+            let text = args[1..].join(" ");
+            let rule: Rule = text.parse().map_err(|err| eyre!("{text}: {err}"))?;
+            println!("{rule}");
+            println!("{}", estimate(&rule, &mut Rng::from_time()));
+            Ok(None)
+        }
+        Some("space") => {
+            space(&args[1..])?;
+            Ok(None)
+        }
+        Some(command @ ("fuzz" | "fuzz-gallery")) => {
+            let steps = parse_number(args.get(1), 10_000)?;
+            let seed = parse_number(args.get(2), Rng::from_time().next_u64())?;
+            println!("fuzzing {steps} keys with seed {seed}");
+            // the default hook would print every caught panic
+            std::panic::set_hook(Box::new(|_| {}));
+            let run = if command == "fuzz" {
+                fuzz
+            } else {
+                fuzz_gallery
+            };
+            run(steps as usize, seed)
+                .map_err(|crash| eyre!("{}\nafter keys {}", crash.message, crash.pressed()))?;
+            println!("no panics");
+            Ok(None)
+        }
+        Some("-h" | "--help") => {
+            println!("{USAGE}");
+            Ok(None)
+        }
+        _ => Err(eyre!("{USAGE}")),
+    }
+}
+
+fn tui_options(args: &[String]) -> Result<App> {
+    let mut app = App::new();
+    let mut bot_master = false;
+    let mut strategies = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--competitive" => app.assistant_enabled = false,
+            "--bot-master" => bot_master = true,
+            "--bots" => {
+                let names = args
+                    .next()
+                    .ok_or_else(|| eyre!("--bots needs strategies"))?;
+                for name in names.split(',') {
+                    let strategy =
+                        Strategy::parse(name).ok_or_else(|| eyre!("unknown strategy {name}"))?;
+                    strategies.push(strategy);
+                }
+            }
+            "--think-ms" => {
+                let ms = args
+                    .next()
+                    .ok_or_else(|| eyre!("--think-ms needs a number"))?;
+                app.think_delay = Duration::from_millis(ms.parse()?);
+            }
+            "--load" => {
+                let path = args.next().ok_or_else(|| eyre!("--load needs a file"))?;
+                let text = std::fs::read_to_string(path)?;
+                app.patterns = parse_game_file(&text).map_err(|err| eyre!("{path}: {err}"))?;
+                app.save_path = path.clone();
+                app.saved_game = gallery_to_game_file(&app.patterns);
+                select_last_pattern(&mut app);
+            }
+            "--confirm" => {
+                let names = args
+                    .next()
+                    .ok_or_else(|| eyre!("--confirm needs actions"))?;
+                app.confirmations = vec![];
+                for name in names.split(',').filter(|name| *name != "none") {
+                    let confirmation = Confirmation::parse(name)
+                        .ok_or_else(|| eyre!("unknown confirmation {name}"))?;
+                    app.confirmations.push(confirmation);
+                }
+            }
+            "--keys" => {
+                let name = args.next().ok_or_else(|| eyre!("--keys needs a preset"))?;
+                app.keymap =
+                    Keymap::parse(name).ok_or_else(|| eyre!("unknown key preset {name}"))?;
+            }
+            _ => return Err(eyre!("unknown option {arg}\n{USAGE}")),
+        }
+    }
+
+    if (bot_master || !strategies.is_empty()) && !app.patterns.is_empty() {
+        return Err(eyre!("--load can not be combined with a game against bots"));
+    }
+    if bot_master || !strategies.is_empty() {
+        start_game(&mut app, bot_master, &strategies);
+    }
+    Ok(app)
+}
+
+fn dump(args: &[String]) -> Result<()> {
+    let mut style = TextStyle::Plain;
+    let mut output = None;
+    let mut notations = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--ansi" => style = TextStyle::Ansi,
+            "-o" => output = Some(args.next().ok_or_else(|| eyre!("-o needs a file"))?),
+            _ => notations.push(arg.clone()),
+        }
+    }
+
+    // A pattern per line is a game file too, one without notes.
+    let patterns = match notations.is_empty() {
+        true => {
+            let text = std::io::read_to_string(std::io::stdin())?;
+            parse_game_file(&text).map_err(|err| eyre!("stdin: {err}"))?
+        }
+        false => parse_patterns(&notations)?,
+    };
+    write_output(output, &gallery_to_text(&patterns, 2, style))
+}
+
+/// Patterns scanned by `space` unless `--limit` says otherwise.
+const DEFAULT_SCAN_LIMIT: u64 = 100_000_000;
+
+fn space(args: &[String]) -> Result<()> {
+    let mut attributes = AttributeSet::all();
+    let mut symmetric = false;
+    let mut limit = DEFAULT_SCAN_LIMIT;
+    let mut words = vec![];
+
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--pieces" => {
+                let letters = args.next().ok_or_else(|| eyre!("--pieces needs letters"))?;
+                attributes = AttributeSet::from_letters(letters)
+                    .map_err(|letter| eyre!("--pieces: unknown letter '{letter}'"))?;
+            }
+            "--symmetric" => symmetric = true,
+            "--limit" => {
+                let value = args.next().ok_or_else(|| eyre!("--limit needs a number"))?;
+                limit = value
+                    .parse()
+                    .map_err(|_| eyre!("--limit: not a number: {value}"))?;
+            }
+            _ => words.push(arg.clone()),
+        }
+    }
+
+    let text = words.join(" ");
+    let rule: Option<Rule> = match text.is_empty() {
+        true => None,
+        false => Some(text.parse().map_err(|err| eyre!("{text}: {err}"))?),
+    };
+
+    let size = attributes.len();
+    match size {
+        Some(size) => println!("{} pieces, {size} patterns", attributes.pieces().len()),
+        None => println!(
+            "{} pieces, too many patterns to count",
+            attributes.pieces().len()
+        ),
+    }
+
+    if let Some(rule) = &rule {
+        let start = Instant::now();
+        let accepted = count_accepted(rule, &attributes);
+        println!(
+            "{rule}: {accepted} accepted, counted in {:.2}s",
+            start.elapsed().as_secs_f64()
+        );
+        if !symmetric {
+            return Ok(());
+        }
+    }
+
+    let start = Instant::now();
+    let mut scanned: u64 = 0;
+    let mut distinct: u64 = 0;
+    for pattern in attributes.patterns().take(limit as usize) {
+        scanned += 1;
+        if symmetric {
+            let code = encode(&pattern);
+            if canonical(&pattern) == code {
+                distinct += 1;
+            }
+            // spot check that codes round trip
+            if scanned.is_multiple_of(4096) && encode(&decode(code)) != code {
+                return Err(eyre!("code {code} does not round trip"));
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+
+    let rate = scanned as f64 / elapsed.as_secs_f64().max(1e-9);
+    println!(
+        "scanned {scanned} in {:.2}s, {rate:.0} patterns/s",
+        elapsed.as_secs_f64()
+    );
+    if symmetric {
+        println!("{distinct} up to rotation and reflection");
+    }
+    if size != Some(scanned) {
+        match size {
+            Some(size) => println!(
+                "the whole space would take about {:.0}s",
+                size as f64 / rate
+            ),
+            None => println!("the whole space is out of reach"),
+        }
+    }
+    Ok(())
+}
+
+fn parse_number(arg: Option<&String>, default: u64) -> Result<u64> {
+    match arg {
+        Some(arg) => arg.parse().map_err(|_| eyre!("not a number: {arg}")),
+        None => Ok(default),
+    }
+}
+
+fn parse_patterns(args: &[String]) -> Result<Vec<PatternParent>> {
+    args.iter()
+        .map(|arg| parse_parent(arg).map_err(|err| eyre!("{arg}: {err}")))
+        .collect()
+}
+
+fn write_output(path: Option<&String>, content: &str) -> Result<()> {
+    match path.map(String::as_str) {
+        None | Some("-") => print!("{content}"),
+        Some(path) => std::fs::write(path, content)?,
+    }
+    Ok(())
+}
+
+/// Two patterns showing every piece: one per pyramid type, with a row per
+/// color and a column per size.
+fn legend() -> Vec<PatternParent> {
+    let colors: [Color; 3] = [COLOR_RED, COLOR_BLUE, COLOR_YELLOW];
+    let sizes = [SymbolSize::SMALL, SymbolSize::MEDIUM, SymbolSize::LARGE];
+
+    [PyramidType::Straight, PyramidType::Angled]
+        .into_iter()
+        .map(|pyramid_type| {
+            let mut pattern = Pattern::default();
+            for (row, color) in colors.iter().enumerate() {
+                for (col, size) in sizes.iter().enumerate() {
+                    pattern.place(Datos {
+                        pos: (col, row),
+                        pyramid_type,
+                        color: *color,
+                        size: *size,
+                        turned: false,
+                    });
+                }
+            }
+            PatternParent::new(pattern, pyramid_type == PyramidType::Straight)
+        })
+        .collect()
+}
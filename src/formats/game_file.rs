@@ -0,0 +1,199 @@
+//! Saved games, as plain text that is easy to read and to diff.
+//!
+//! Every koan is one line of pattern notation with its verdict, see
+//! [`super::notation`]. The lines after it may annotate it:
+//!
+//! ```text
+//! # zendui game
+//! + Lr^ . Sb/
+//! title: two colors
+//! note: testing whether size matters
+//! tags: size color
+//! - Mb^
+//! ```
+//!
+//! Blank lines and lines starting with `#` are skipped.
+
+use std::fmt;
+
+use crate::PatternParent;
+
+use super::notation::{NotationError, parent_to_notation, parse_parent};
+
+const HEADER: &str = "# zendui game";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameFileErrorKind {
+    Notation(NotationError),
+    UnknownField(String),
+    /// A second line for the same field of a koan, which would replace the
+    /// first.
+    RepeatedField(String),
+    /// An annotation line before the first koan.
+    NoKoan,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GameFileError {
+    pub kind: GameFileErrorKind,
+    /// One based, like editors show it.
+    pub line: usize,
+}
+
+impl fmt::Display for GameFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}: ", self.line)?;
+        match &self.kind {
+            GameFileErrorKind::Notation(err) => write!(f, "{err}"),
+            GameFileErrorKind::UnknownField(field) => write!(f, "unknown field '{field}'"),
+            GameFileErrorKind::RepeatedField(field) => {
+                write!(f, "the koan already has a {field}")
+            }
+            GameFileErrorKind::NoKoan => f.write_str("annotation before the first koan"),
+        }
+    }
+}
+
+impl std::error::Error for GameFileError {}
+
+pub fn gallery_to_game_file(patterns: &[PatternParent]) -> String {
+    let mut out = format!("{HEADER}\n");
+    for pattern in patterns {
+        out.push_str(&parent_to_notation(pattern));
+        out.push('\n');
+        let annotation = &pattern.annotation;
+        if !annotation.title.is_empty() {
+            out.push_str(&format!("title: {}\n", annotation.title));
+        }
+        if !annotation.note.is_empty() {
+            out.push_str(&format!("note: {}\n", annotation.note));
+        }
+        if !annotation.tags.is_empty() {
+            out.push_str(&format!("tags: {}\n", annotation.tags.join(" ")));
+        }
+    }
+    out
+}
+
+pub fn parse_game_file(text: &str) -> Result<Vec<PatternParent>, GameFileError> {
+    let mut patterns: Vec<PatternParent> = vec![];
+
+    for (index, line) in text.lines().enumerate() {
+        let error = |kind| GameFileError {
+            kind,
+            line: index + 1,
+        };
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        let field = line
+            .split_once(':')
+            .filter(|(name, _)| name.chars().all(|c| c.is_ascii_lowercase()));
+        let Some((name, value)) = field else {
+            let pattern =
+                parse_parent(line).map_err(|err| error(GameFileErrorKind::Notation(err)))?;
+            patterns.push(pattern);
+            continue;
+        };
+
+        let annotation = match patterns.last_mut() {
+            Some(pattern) => &mut pattern.annotation,
+            None => return Err(error(GameFileErrorKind::NoKoan)),
+        };
+        let value = value.trim();
+        let repeated = match name {
+            "title" => !std::mem::replace(&mut annotation.title, value.to_string()).is_empty(),
+            "note" => !std::mem::replace(&mut annotation.note, value.to_string()).is_empty(),
+            "tags" => !std::mem::replace(&mut annotation.tags, parse_tags(value)).is_empty(),
+            _ => return Err(error(GameFileErrorKind::UnknownField(name.to_string()))),
+        };
+        if repeated {
+            return Err(error(GameFileErrorKind::RepeatedField(name.to_string())));
+        }
+    }
+    Ok(patterns)
+}
+
+/// Tags are separated by spaces or commas.
+pub fn parse_tags(text: &str) -> Vec<String> {
+    text.split([' ', ','])
+        .filter(|tag| !tag.is_empty())
+        .map(str::to_string)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Annotation;
+
+    fn koan(notation: &str, title: &str, note: &str, tags: &[&str]) -> PatternParent {
+        PatternParent {
+            annotation: Annotation {
+                title: title.to_string(),
+                note: note.to_string(),
+                tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            },
+            ..parse_parent(notation).unwrap()
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let gallery = vec![
+            koan(
+                "+ Lr^ . Sb/",
+                "two colors",
+                "testing whether size matters",
+                &[],
+            ),
+            koan(
+                "- Mb^ / . Sy\\",
+                "",
+                "# is not a comment here: see #0",
+                &["size", "color"],
+            ),
+            koan("+ .", "#1 again", "", &["empty"]),
+            koan("- . . Ly/", "", "", &[]),
+        ];
+        let text = gallery_to_game_file(&gallery);
+        assert_eq!(parse_game_file(&text), Ok(gallery));
+    }
+
+    #[test]
+    fn skips_comments_and_blank_lines() {
+        let text = "# zendui game\n\n+ Lr^\n  # a comment\ntitle: kept\n";
+        let expected = vec![koan("+ Lr^", "kept", "", &[])];
+        assert_eq!(parse_game_file(text), Ok(expected));
+    }
+
+    #[test]
+    fn rejects_malformed_annotations() {
+        let error = |text: &str| parse_game_file(text).unwrap_err();
+
+        let err = error("+ Lr^\ntitel: typo");
+        assert_eq!(err.line, 2);
+        assert_eq!(
+            err.kind,
+            GameFileErrorKind::UnknownField("titel".to_string())
+        );
+
+        let err = error("title: first\n+ Lr^");
+        assert_eq!((err.line, err.kind), (1, GameFileErrorKind::NoKoan));
+
+        let err = error("+ Lr^\nnote: one\nnote: two");
+        assert_eq!(err.line, 3);
+        assert_eq!(
+            err.kind,
+            GameFileErrorKind::RepeatedField("note".to_string())
+        );
+
+        for text in ["+ Lr^\ntitle two colors", "+ Lr^\nTitle: two colors"] {
+            let err = error(text);
+            assert_eq!(err.line, 2);
+            assert!(matches!(err.kind, GameFileErrorKind::Notation(_)));
+        }
+    }
+}
@@ -0,0 +1,4 @@
+pub mod game_file;
+pub mod notation;
+pub mod svg;
+pub mod text;
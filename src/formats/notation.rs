@@ -0,0 +1,342 @@
+//! Compact text notation for patterns, handy in chat messages and fixtures.
+//!
+//! A pattern is written row by row, top to bottom, with `/` between rows:
+//!
+//! ```text
+//! Lr^ . Sb/ / . . Mya
+//! ```
+//!
+//! Every whitespace separated token in a row is one cell, left to right:
+//!
+//! * `.` is an empty cell
+//! * a piece is three characters: size, color and type
+//!   * size: `S`mall, `M`edium or `L`arge
+//!   * color: `r`ed, `b`lue or `y`ellow
+//!   * type: `^` straight, `/` angled (`a` is accepted for angled too), `\`
+//!     angled and turned a quarter
+//!
+//! Cells missing at the end of a row, and rows missing at the end of the
+//! pattern, are empty. An empty pattern is written as a single `.`.
+//!
+//! A saved pattern may start with a verdict token, `+` for valid and `-` for
+//! invalid, e.g. `- Mb^ / . Sy/`.
+
+use std::fmt;
+
+use ratatui::style::Color;
+
+use crate::{
+    COLOR_BLUE, COLOR_RED, COLOR_YELLOW, Datos, HEIGHT, Pattern, PatternParent, PyramidType,
+    SymbolSize, WIDTH,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NotationErrorKind {
+    UnknownSize(char),
+    UnknownColor(char),
+    UnknownType(char),
+    WrongLength,
+    TooManyCells,
+    TooManyRows,
+    MisplacedVerdict,
+}
+
+/// A parse error pointing at the offending token.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NotationError {
+    pub kind: NotationErrorKind,
+    /// The offending token as written.
+    pub token: String,
+    /// Zero based index of the token among all tokens.
+    pub token_index: usize,
+    /// Zero based character column where the token starts.
+    pub column: usize,
+}
+
+impl fmt::Display for NotationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match &self.kind {
+            NotationErrorKind::UnknownSize(c) => format!("unknown size '{c}', expected S, M or L"),
+            NotationErrorKind::UnknownColor(c) => {
+                format!("unknown color '{c}', expected r, b or y")
+            }
+            NotationErrorKind::UnknownType(c) => format!("unknown type '{c}', expected ^, / or \\"),
+            NotationErrorKind::WrongLength => {
+                "a piece is size, color and type, e.g. Lr^".to_string()
+            }
+            NotationErrorKind::TooManyCells => format!("a row has at most {WIDTH} cells"),
+            NotationErrorKind::TooManyRows => format!("a pattern has at most {HEIGHT} rows"),
+            NotationErrorKind::MisplacedVerdict => "+ or - may only be the first token".to_string(),
+        };
+        write!(
+            f,
+            "token {} '{}' at column {}: {reason}",
+            self.token_index + 1,
+            self.token,
+            self.column + 1
+        )
+    }
+}
+
+impl std::error::Error for NotationError {}
+
+/// Splits on whitespace while remembering the column of every token.
+fn tokens(text: &str) -> Vec<(usize, &str)> {
+    let mut tokens = vec![];
+    let mut start = None;
+
+    for (column, (byte_index, c)) in text.char_indices().enumerate() {
+        match (c.is_whitespace(), start) {
+            (false, None) => start = Some((column, byte_index)),
+            (true, Some((token_column, token_start))) => {
+                tokens.push((token_column, &text[token_start..byte_index]));
+                start = None;
+            }
+            _ => (),
+        }
+    }
+    if let Some((token_column, token_start)) = start {
+        tokens.push((token_column, &text[token_start..]));
+    }
+    tokens
+}
+
+/// Parses a pattern with an optional leading `+`/`-` verdict. Patterns without
+/// a verdict are valid.
+pub fn parse_parent(text: &str) -> Result<PatternParent, NotationError> {
+    let mut pattern = Pattern::default();
+    let mut valid = true;
+    let (mut row, mut col) = (0, 0);
+
+    for (token_index, (column, token)) in tokens(text).into_iter().enumerate() {
+        let error = |kind| NotationError {
+            kind,
+            token: token.to_string(),
+            token_index,
+            column,
+        };
+
+        match token {
+            "+" | "-" if token_index == 0 => valid = token == "+",
+            "+" | "-" => return Err(error(NotationErrorKind::MisplacedVerdict)),
+            "/" => {
+                row += 1;
+                col = 0;
+                if row >= HEIGHT {
+                    return Err(error(NotationErrorKind::TooManyRows));
+                }
+            }
+            _ => {
+                if col >= WIDTH {
+                    return Err(error(NotationErrorKind::TooManyCells));
+                }
+                if token != "." {
+                    let (pyramid_type, turned, color, size) = parse_piece(token).map_err(error)?;
+                    pattern.place(Datos {
+                        pos: (col, row),
+                        pyramid_type,
+                        color,
+                        size,
+                        turned,
+                    });
+                }
+                col += 1;
+            }
+        }
+    }
+
+    Ok(PatternParent::new(pattern, valid))
+}
+
+/// The type, whether it is turned, the color and the size of a piece token.
+fn parse_piece(token: &str) -> Result<(PyramidType, bool, Color, SymbolSize), NotationErrorKind> {
+    let chars: Vec<char> = token.chars().collect();
+    let [size, color, pyramid_type] = chars[..] else {
+        return Err(NotationErrorKind::WrongLength);
+    };
+
+    let size = match size {
+        'S' => SymbolSize::SMALL,
+        'M' => SymbolSize::MEDIUM,
+        'L' => SymbolSize::LARGE,
+        other => return Err(NotationErrorKind::UnknownSize(other)),
+    };
+    let color = match color {
+        'r' => COLOR_RED,
+        'b' => COLOR_BLUE,
+        'y' => COLOR_YELLOW,
+        other => return Err(NotationErrorKind::UnknownColor(other)),
+    };
+    let (pyramid_type, turned) = match pyramid_type {
+        '^' => (PyramidType::Straight, false),
+        '/' | 'a' => (PyramidType::Angled, false),
+        '\\' => (PyramidType::Angled, true),
+        other => return Err(NotationErrorKind::UnknownType(other)),
+    };
+    Ok((pyramid_type, turned, color, size))
+}
+
+/// Writes a pattern in notation, dropping trailing empty cells and rows.
+pub fn pattern_to_notation(pattern: &Pattern) -> String {
+    let mut rows: Vec<String> = (0..HEIGHT)
+        .map(|row| {
+            let mut cells: Vec<String> = (0..WIDTH)
+                .map(|col| match pattern.pyramid_at((col, row)) {
+                    Some(pyramid) => piece_to_notation(&pyramid),
+                    None => ".".to_string(),
+                })
+                .collect();
+            while cells.last().is_some_and(|cell| cell == ".") {
+                cells.pop();
+            }
+            cells.join(" ")
+        })
+        .collect();
+
+    while rows.last().is_some_and(String::is_empty) {
+        rows.pop();
+    }
+    if rows.is_empty() {
+        return ".".to_string();
+    }
+
+    // An empty row in the middle still needs a token to keep its place.
+    rows.iter()
+        .map(|row| if row.is_empty() { "." } else { row.as_str() })
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+/// Writes a saved pattern with its verdict, e.g. `+ Lr^ / . Sb/`.
+pub fn parent_to_notation(parent: &PatternParent) -> String {
+    let verdict = if parent.valid { "+" } else { "-" };
+    format!("{verdict} {}", pattern_to_notation(&parent.data))
+}
+
+fn piece_to_notation(pyramid: &Datos) -> String {
+    let size = match pyramid.size {
+        SymbolSize::SMALL => 'S',
+        SymbolSize::MEDIUM => 'M',
+        SymbolSize::LARGE => 'L',
+    };
+    let color = match pyramid.color {
+        COLOR_RED => 'r',
+        COLOR_BLUE => 'b',
+        COLOR_YELLOW => 'y',
+        _ => '?',
+    };
+    let pyramid_type = match (pyramid.pyramid_type, pyramid.turned) {
+        (PyramidType::Straight, _) => '^',
+        (PyramidType::Angled, false) => '/',
+        (PyramidType::Angled, true) => '\\',
+    };
+    format!("{size}{color}{pyramid_type}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(text: &str) -> PatternParent {
+        parse_parent(text).unwrap_or_else(|err| panic!("{text}: {err}"))
+    }
+
+    fn error(text: &str) -> NotationError {
+        match parse_parent(text) {
+            Ok(_) => panic!("{text} parsed"),
+            Err(err) => err,
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        for text in [
+            "+ .",
+            "- .",
+            "+ Lr^",
+            "+ Lr^ . Sb/ / . . My\\",
+            "- Sy^ Mr/ Lb\\ / Mb^ Mb^ Mb^ / . . Sr/",
+            "+ . . Lb^",
+            // an empty middle row keeps its place
+            "+ Sr^ / . / Sb^",
+            "+ . / . / Ly/",
+        ] {
+            assert_eq!(parent_to_notation(&parse(text)), text);
+        }
+    }
+
+    #[test]
+    fn writes_the_shortest_form() {
+        assert_eq!(parent_to_notation(&parse("")), "+ .");
+        assert_eq!(parent_to_notation(&parse(". . . / . / .")), "+ .");
+        assert_eq!(parent_to_notation(&parse("Mra . .")), "+ Mr/");
+        assert_eq!(parent_to_notation(&parse("Sb^ / . . . /")), "+ Sb^");
+    }
+
+    #[test]
+    fn reads_pieces() {
+        let pattern = parse("- . Lr^ / . . Sy\\").data;
+        let top = pattern.pyramid_at((1, 0)).unwrap();
+        assert_eq!(
+            (top.size, top.color, top.pyramid_type),
+            (SymbolSize::LARGE, COLOR_RED, PyramidType::Straight)
+        );
+        let bottom = pattern.pyramid_at((2, 1)).unwrap();
+        assert_eq!(
+            (
+                bottom.size,
+                bottom.color,
+                bottom.pyramid_type,
+                bottom.turned
+            ),
+            (SymbolSize::SMALL, COLOR_YELLOW, PyramidType::Angled, true)
+        );
+        assert_eq!(pattern.len(), 2);
+        assert!(!parse("- .").valid);
+        assert!(parse(".").valid);
+    }
+
+    #[test]
+    fn points_at_the_bad_token() {
+        let err = error("+ Lr^  Xb/");
+        assert_eq!(err.kind, NotationErrorKind::UnknownSize('X'));
+        assert_eq!(
+            (err.token.as_str(), err.token_index, err.column),
+            ("Xb/", 2, 7)
+        );
+        assert_eq!(
+            err.to_string(),
+            "token 3 'Xb/' at column 8: unknown size 'X', expected S, M or L"
+        );
+
+        let err = error("Lg^");
+        assert_eq!(err.kind, NotationErrorKind::UnknownColor('g'));
+        assert_eq!(err.column, 0);
+
+        let err = error(". Lr?");
+        assert_eq!(err.kind, NotationErrorKind::UnknownType('?'));
+        assert_eq!(err.column, 2);
+
+        let err = error("Lr");
+        assert_eq!(err.kind, NotationErrorKind::WrongLength);
+
+        let err = error(". . . Sr^");
+        assert_eq!(err.kind, NotationErrorKind::TooManyCells);
+        assert_eq!((err.token_index, err.column), (3, 6));
+
+        let err = error(". / . / . / .");
+        assert_eq!(err.kind, NotationErrorKind::TooManyRows);
+        assert_eq!((err.token_index, err.column), (5, 10));
+
+        let err = error("Sr^ +");
+        assert_eq!(err.kind, NotationErrorKind::MisplacedVerdict);
+        assert_eq!(err.column, 4);
+    }
+
+    #[test]
+    fn counts_columns_in_characters() {
+        // a no-break space is whitespace of two bytes but one column
+        let err = error(". \u{a0}Xr^");
+        assert_eq!((err.token_index, err.column), (1, 3));
+    }
+}
@@ -0,0 +1,193 @@
+use std::fmt::Write;
+
+use ratatui::style::Color;
+
+use crate::{
+    Datos, HEIGHT, PATTERN_BORDER_INVALID, PATTERN_BORDER_VALID, PatternParent, PyramidType,
+    SymbolSize, WIDTH,
+};
+
+const CELL: usize = 60;
+const PADDING: usize = 6;
+const TITLE_HEIGHT: usize = 18;
+const GAP: usize = 12;
+
+const TILE_WIDTH: usize = WIDTH * CELL + 2 * PADDING;
+const TILE_HEIGHT: usize = HEIGHT * CELL + 2 * PADDING + TITLE_HEIGHT;
+
+/// Renders a single pattern as a standalone SVG document.
+pub fn pattern_to_svg(pattern: &PatternParent, index: usize) -> String {
+    let mut body = String::new();
+    write_tile(&mut body, pattern, index, 0, 0);
+    document(TILE_WIDTH, TILE_HEIGHT, &body)
+}
+
+/// Renders the whole gallery, `per_row` patterns to a row, as one SVG document.
+pub fn gallery_to_svg(patterns: &[PatternParent], per_row: usize) -> String {
+    let per_row = per_row.max(1);
+    let columns = patterns.len().clamp(1, per_row);
+    let rows = patterns.len().div_ceil(per_row).max(1);
+
+    let mut body = String::new();
+    for (index, pattern) in patterns.iter().enumerate() {
+        let x = GAP + (index % per_row) * (TILE_WIDTH + GAP);
+        let y = GAP + (index / per_row) * (TILE_HEIGHT + GAP);
+        write_tile(&mut body, pattern, index, x, y);
+    }
+
+    let width = GAP + columns * (TILE_WIDTH + GAP);
+    let height = GAP + rows * (TILE_HEIGHT + GAP);
+    document(width, height, &body)
+}
+
+fn document(width: usize, height: usize, body: &str) -> String {
+    format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{width}\" height=\"{height}\" viewBox=\"0 0 {width} {height}\">\n\
+         <rect width=\"{width}\" height=\"{height}\" fill=\"#1e1e1e\"/>\n\
+         {body}</svg>\n"
+    )
+}
+
+fn write_tile(out: &mut String, pattern: &PatternParent, index: usize, x: usize, y: usize) {
+    let border = if pattern.valid {
+        PATTERN_BORDER_VALID
+    } else {
+        PATTERN_BORDER_INVALID
+    };
+    let verdict = if pattern.valid { "valid" } else { "invalid" };
+
+    let _ = writeln!(out, "<g transform=\"translate({x},{y})\">");
+    let _ = writeln!(
+        out,
+        "<rect x=\"1\" y=\"1\" width=\"{}\" height=\"{}\" rx=\"4\" fill=\"none\" stroke=\"{}\" stroke-width=\"2\"/>",
+        TILE_WIDTH - 2,
+        TILE_HEIGHT - 2,
+        hex(border)
+    );
+    let _ = writeln!(
+        out,
+        "<text x=\"{PADDING}\" y=\"{}\" font-family=\"monospace\" font-size=\"13\" fill=\"{}\">#{index} {verdict}</text>",
+        TITLE_HEIGHT - 4,
+        hex(border)
+    );
+
+    for row in 0..HEIGHT {
+        for col in 0..WIDTH {
+            let cell_x = PADDING + col * CELL;
+            let cell_y = TITLE_HEIGHT + PADDING + row * CELL;
+            let _ = writeln!(
+                out,
+                "<rect x=\"{cell_x}\" y=\"{cell_y}\" width=\"{CELL}\" height=\"{CELL}\" fill=\"none\" stroke=\"#4a4a4a\"/>"
+            );
+            if let Some(pyramid) = pattern.data.pyramid_at((col, row)) {
+                write_pyramid(out, &pyramid, cell_x + CELL / 2, cell_y + CELL / 2);
+            }
+        }
+    }
+    out.push_str("</g>\n");
+}
+
+/// Draws a pyramid seen from above, centered on `(cx, cy)`. The faces are
+/// split like the terminal renderer: two solid faces and two shaded ones.
+fn write_pyramid(out: &mut String, pyramid: &Datos, cx: usize, cy: usize) {
+    let half = match pyramid.size {
+        SymbolSize::SMALL => 12,
+        SymbolSize::MEDIUM => 18,
+        SymbolSize::LARGE => 25,
+    };
+    let (cx, cy) = (cx as i64, cy as i64);
+
+    let corners = match pyramid.pyramid_type {
+        // corners of the square, clockwise from the top left
+        PyramidType::Straight => [
+            (cx - half, cy - half),
+            (cx + half, cy - half),
+            (cx + half, cy + half),
+            (cx - half, cy + half),
+        ],
+        // the same square turned 45 degrees
+        PyramidType::Angled => [
+            (cx, cy - half),
+            (cx + half, cy),
+            (cx, cy + half),
+            (cx - half, cy),
+        ],
+    };
+
+    let color = hex(pyramid.color);
+    for face in 0..4 {
+        let (ax, ay) = corners[face];
+        let (bx, by) = corners[(face + 1) % 4];
+        // every other face is solid, matching the █ and ▒ halves in the terminal
+        let opacity = if (face % 2 == 1) != pyramid.turned {
+            "1"
+        } else {
+            "0.55"
+        };
+        let _ = writeln!(
+            out,
+            "<polygon points=\"{ax},{ay} {bx},{by} {cx},{cy}\" fill=\"{color}\" fill-opacity=\"{opacity}\" stroke=\"{color}\"/>"
+        );
+    }
+}
+
+fn hex(color: Color) -> String {
+    match color {
+        Color::Rgb(r, g, b) => format!("#{r:02x}{g:02x}{b:02x}"),
+        Color::Red | Color::LightRed => "#e67c7c".to_string(),
+        Color::Blue | Color::LightBlue => "#5460de".to_string(),
+        Color::Yellow | Color::LightYellow => "#ebd375".to_string(),
+        Color::Green | Color::LightGreen => "#379637".to_string(),
+        Color::Black => "#000000".to_string(),
+        _ => "#ffffff".to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::notation::parse_parent;
+
+    #[test]
+    fn draws_a_koan() {
+        let koan = parse_parent("- . / . Sr^ Mb\\").unwrap();
+        let expected = r##"<svg xmlns="http://www.w3.org/2000/svg" width="192" height="210" viewBox="0 0 192 210">
+<rect width="192" height="210" fill="#1e1e1e"/>
+<g transform="translate(0,0)">
+<rect x="1" y="1" width="190" height="208" rx="4" fill="none" stroke="#963737" stroke-width="2"/>
+<text x="6" y="14" font-family="monospace" font-size="13" fill="#963737">#4 invalid</text>
+<rect x="6" y="24" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+<rect x="66" y="24" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+<rect x="126" y="24" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+<rect x="6" y="84" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+<rect x="66" y="84" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+<polygon points="84,102 108,102 96,114" fill="#e67c7c" fill-opacity="0.55" stroke="#e67c7c"/>
+<polygon points="108,102 108,126 96,114" fill="#e67c7c" fill-opacity="1" stroke="#e67c7c"/>
+<polygon points="108,126 84,126 96,114" fill="#e67c7c" fill-opacity="0.55" stroke="#e67c7c"/>
+<polygon points="84,126 84,102 96,114" fill="#e67c7c" fill-opacity="1" stroke="#e67c7c"/>
+<rect x="126" y="84" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+<polygon points="156,96 174,114 156,114" fill="#5460de" fill-opacity="1" stroke="#5460de"/>
+<polygon points="174,114 156,132 156,114" fill="#5460de" fill-opacity="0.55" stroke="#5460de"/>
+<polygon points="156,132 138,114 156,114" fill="#5460de" fill-opacity="1" stroke="#5460de"/>
+<polygon points="138,114 156,96 156,114" fill="#5460de" fill-opacity="0.55" stroke="#5460de"/>
+<rect x="6" y="144" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+<rect x="66" y="144" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+<rect x="126" y="144" width="60" height="60" fill="none" stroke="#4a4a4a"/>
+</g>
+</svg>
+"##;
+        assert_eq!(pattern_to_svg(&koan, 4), expected);
+    }
+
+    #[test]
+    fn lays_out_a_gallery() {
+        let koan = parse_parent("+ .").unwrap();
+        let svg = gallery_to_svg(&vec![koan; 3], 2);
+        assert!(svg.starts_with(
+            r#"<svg xmlns="http://www.w3.org/2000/svg" width="420" height="456" viewBox="0 0 420 456">"#
+        ));
+        for (x, y) in [(12, 12), (216, 12), (12, 234)] {
+            assert!(svg.contains(&format!("<g transform=\"translate({x},{y})\">")));
+        }
+    }
+}
@@ -0,0 +1,214 @@
+use ratatui::style::Color;
+
+use crate::{
+    COLOR_BLUE, COLOR_RED, COLOR_YELLOW, HEIGHT, PATTERN_BORDER_INVALID, PATTERN_BORDER_VALID,
+    PatternParent, PyramidType, WIDTH,
+    ui::footer::{top_down_pyramid_angled_lines, top_down_pyramid_lines},
+};
+
+/// Width and height of a cell, enough for the largest pyramid plus a margin.
+const CELL_WIDTH: usize = 18;
+const CELL_HEIGHT: usize = 8;
+
+const TILE_WIDTH: usize = WIDTH * CELL_WIDTH + 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TextStyle {
+    /// Box drawing characters and 24-bit ANSI colors.
+    Ansi,
+    /// Only ASCII. Pieces are drawn with the initial of their color, upper
+    /// case for the solid faces and lower case for the shaded ones.
+    Plain,
+}
+
+/// Dumps the whole gallery, `per_row` patterns side by side.
+pub fn gallery_to_text(patterns: &[PatternParent], per_row: usize, style: TextStyle) -> String {
+    let tiles: Vec<Vec<String>> = patterns
+        .iter()
+        .enumerate()
+        .map(|(index, pattern)| tile_lines(pattern, index, style))
+        .collect();
+
+    let mut out = String::new();
+    for row in tiles.chunks(per_row.max(1)) {
+        for line_index in 0..row[0].len() {
+            let line: Vec<&str> = row.iter().map(|tile| tile[line_index].as_str()).collect();
+            out.push_str(line.join(" ").trim_end());
+            out.push('\n');
+        }
+    }
+    out
+}
+
+/// Dumps a single pattern.
+pub fn pattern_to_text(pattern: &PatternParent, index: usize, style: TextStyle) -> String {
+    let mut out = tile_lines(pattern, index, style).join("\n");
+    out.push('\n');
+    out
+}
+
+fn tile_lines(pattern: &PatternParent, index: usize, style: TextStyle) -> Vec<String> {
+    let border_color = if pattern.valid {
+        PATTERN_BORDER_VALID
+    } else {
+        PATTERN_BORDER_INVALID
+    };
+    let verdict = if pattern.valid { "valid" } else { "invalid" };
+    let title = format!("#{index} {verdict}");
+
+    let (top_left, top_right, bottom_left, bottom_right, horizontal, vertical) = match style {
+        TextStyle::Ansi => ("┌", "┐", "└", "┘", "─", "│"),
+        TextStyle::Plain => ("+", "+", "+", "+", "-", "|"),
+    };
+    let border = |text: String| match style {
+        TextStyle::Ansi => paint(&text, border_color),
+        TextStyle::Plain => text,
+    };
+
+    let mut lines = vec![];
+    let fill = horizontal.repeat(TILE_WIDTH - 2 - title.len());
+    lines.push(border(format!("{top_left}{title}{fill}{top_right}")));
+
+    for row in 0..HEIGHT {
+        let cells: Vec<Vec<String>> = (0..WIDTH)
+            .map(|col| cell_lines(pattern, (col, row), style))
+            .collect();
+        for line_index in 0..CELL_HEIGHT {
+            let mut line = border(vertical.to_string());
+            for cell in &cells {
+                line.push_str(&cell[line_index]);
+            }
+            line.push_str(&border(vertical.to_string()));
+            lines.push(line);
+        }
+    }
+
+    let bottom = horizontal.repeat(TILE_WIDTH - 2);
+    lines.push(border(format!("{bottom_left}{bottom}{bottom_right}")));
+    lines
+}
+
+/// `CELL_HEIGHT` lines that are each `CELL_WIDTH` columns wide when printed.
+fn cell_lines(pattern: &PatternParent, pos: (usize, usize), style: TextStyle) -> Vec<String> {
+    let Some(pyramid) = pattern.data.pyramid_at(pos) else {
+        return vec![" ".repeat(CELL_WIDTH); CELL_HEIGHT];
+    };
+
+    let mut lines = match pyramid.pyramid_type {
+        PyramidType::Straight => top_down_pyramid_lines(pyramid.size),
+        PyramidType::Angled => top_down_pyramid_angled_lines(pyramid.size, pyramid.turned),
+    };
+    lines.resize(CELL_HEIGHT, String::new());
+
+    lines
+        .into_iter()
+        .map(|line| {
+            let width = line.chars().count();
+            let left = (CELL_WIDTH - width) / 2;
+            let right = CELL_WIDTH - width - left;
+            let line = match style {
+                TextStyle::Ansi => paint(&line, pyramid.color),
+                TextStyle::Plain => plain(&line, pyramid.color),
+            };
+            format!("{}{line}{}", " ".repeat(left), " ".repeat(right))
+        })
+        .collect()
+}
+
+fn paint(text: &str, color: Color) -> String {
+    match color {
+        _ if text.trim().is_empty() => text.to_string(),
+        Color::Rgb(r, g, b) => format!("\x1b[38;2;{r};{g};{b}m{text}\x1b[0m"),
+        _ => text.to_string(),
+    }
+}
+
+fn plain(text: &str, color: Color) -> String {
+    let (solid, shaded) = match color {
+        COLOR_RED => ('R', 'r'),
+        COLOR_BLUE => ('B', 'b'),
+        COLOR_YELLOW => ('Y', 'y'),
+        _ => ('#', ':'),
+    };
+    text.chars()
+        .map(|c| match c {
+            '█' => solid,
+            '▒' => shaded,
+            other => other,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::notation::parse_parent;
+
+    #[test]
+    fn dumps_plain_text() {
+        let koan = parse_parent("+ . Sr^ / . Mb\\").unwrap();
+        let expected = "\
++#0 valid----------------------------------------------+\n\
+|                                                      |\n\
+|                                                      |\n\
+|                       RrrrrrrR                       |\n\
+|                       RRRrrRRR                       |\n\
+|                       RrrrrrrR                       |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                          bB                          |\n\
+|                        bbbBBB                        |\n\
+|                      bbbbbBBBBB                      |\n\
+|                      BBBBBbbbbb                      |\n\
+|                        BBBbbb                        |\n\
+|                          Bb                          |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
+|                                                      |\n\
++------------------------------------------------------+\n";
+        assert_eq!(pattern_to_text(&koan, 0, TextStyle::Plain), expected);
+    }
+
+    #[test]
+    fn paints_ansi_colors() {
+        let koan = parse_parent("- Sy^").unwrap();
+        let text = pattern_to_text(&koan, 0, TextStyle::Ansi);
+        let lines: Vec<&str> = text.lines().collect();
+        let border = "\x1b[38;2;150;55;55m";
+        let yellow = "\x1b[38;2;235;211;117m";
+        let reset = "\x1b[0m";
+        let side = format!("{border}│{reset}");
+        assert_eq!(lines.len(), HEIGHT * CELL_HEIGHT + 2);
+        assert_eq!(
+            lines[0],
+            format!("{border}┌#0 invalid{}┐{reset}", "─".repeat(44))
+        );
+        assert_eq!(lines[1], format!("{side}{}{side}", " ".repeat(54)));
+        assert_eq!(
+            lines[4],
+            format!("{side}     {yellow}███▒▒███{reset}{}{side}", " ".repeat(41))
+        );
+        assert_eq!(lines[25], format!("{border}└{}┘{reset}", "─".repeat(54)));
+    }
+
+    #[test]
+    fn lines_up_a_gallery() {
+        let koans = vec![parse_parent("+ .").unwrap(); 3];
+        let text = gallery_to_text(&koans, 2, TextStyle::Plain);
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines.len(), 2 * (HEIGHT * CELL_HEIGHT + 2));
+        assert_eq!(
+            lines[0],
+            format!("+#0 valid{0}+ +#1 valid{0}+", "-".repeat(46))
+        );
+        assert_eq!(lines[26], format!("+#2 valid{}+", "-".repeat(46)));
+    }
+}
@@ -0,0 +1,137 @@
+use std::fmt;
+
+use crate::{
+    helpers::rng::Rng,
+    solver::{
+        Solver,
+        advisor::{mutate, random_pattern, suggest},
+        rule::Rule,
+    },
+};
+
+use super::{Action, Game, Phase, Player, SeatId};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Strategy {
+    /// Random koans and bets, guesses a random possible rule now and then.
+    Random,
+    /// Builds the most informative koan and guesses as soon as one rule
+    /// clearly stands out.
+    Greedy,
+    /// Small changes to known koans, only bets when sure, and only guesses
+    /// when a single rule is left.
+    Conservative,
+}
+
+impl Strategy {
+    pub const ALL: [Strategy; 3] = [Strategy::Random, Strategy::Greedy, Strategy::Conservative];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|strategy| strategy.to_string() == name)
+    }
+}
+
+impl fmt::Display for Strategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Strategy::Random => "random",
+            Strategy::Greedy => "greedy",
+            Strategy::Conservative => "conservative",
+        };
+        f.write_str(name)
+    }
+}
+
+/// A computer guesser.
+#[derive(Debug)]
+pub struct BotGuesser {
+    strategy: Strategy,
+    rng: Rng,
+    solver: Solver,
+}
+
+impl BotGuesser {
+    pub fn new(strategy: Strategy, rng: Rng) -> Self {
+        Self {
+            strategy,
+            rng,
+            solver: Solver::new(),
+        }
+    }
+}
+
+fn submit(strategy: Strategy, rng: &mut Rng, game: &Game, rules: &[&Rule]) -> Action {
+    let pattern = match strategy {
+        Strategy::Random => random_pattern(rng),
+        Strategy::Greedy => suggest(rules, &game.koans, rng)
+            .into_iter()
+            .next()
+            .map(|suggestion| suggestion.pattern)
+            .unwrap_or_else(|| random_pattern(rng)),
+        Strategy::Conservative if game.koans.is_empty() => random_pattern(rng),
+        Strategy::Conservative => {
+            let base = &rng.pick(&game.koans).data;
+            mutate(base, rng)
+        }
+    };
+
+    let valid_rules = rules.iter().filter(|rule| rule.holds(&pattern)).count();
+    let prediction = match strategy {
+        _ if rules.is_empty() => None,
+        Strategy::Random => Some(rng.pick(rules).holds(&pattern)),
+        Strategy::Greedy => Some(valid_rules * 2 >= rules.len()),
+        Strategy::Conservative if valid_rules == 0 => Some(false),
+        Strategy::Conservative if valid_rules == rules.len() => Some(true),
+        Strategy::Conservative => None,
+    };
+
+    Action::Submit {
+        pattern,
+        prediction,
+    }
+}
+
+fn guess(strategy: Strategy, rng: &mut Rng, rules: &[&Rule], tokens: usize) -> Option<Rule> {
+    if tokens == 0 || rules.is_empty() {
+        return None;
+    }
+
+    let simplest = rules[0];
+    let wants_to_guess = match strategy {
+        Strategy::Random => rng.below(3) == 0,
+        // guess once the simplest rule outweighs all the others together
+        Strategy::Greedy => {
+            let weight = |rule: &Rule| 0.5_f64.powi(rule.complexity() as i32);
+            let total: f64 = rules.iter().map(|rule| weight(rule)).sum();
+            rules.len() <= 2 || weight(simplest) * 2.0 > total
+        }
+        Strategy::Conservative => rules.len() == 1,
+    };
+
+    match (wants_to_guess, strategy) {
+        (false, _) => None,
+        (true, Strategy::Random) => Some((*rng.pick(rules)).clone()),
+        (true, _) => Some(simplest.clone()),
+    }
+}
+
+impl Player for BotGuesser {
+    fn act(&mut self, game: &Game, seat: SeatId) -> Option<Action> {
+        if !matches!(game.phase, Phase::Turn(_)) {
+            return None;
+        }
+
+        let rules: Vec<&Rule> = self.solver.consistent(&game.koans).collect();
+        match guess(
+            self.strategy,
+            &mut self.rng,
+            &rules,
+            game.seats[seat].tokens,
+        ) {
+            Some(rule) => Some(Action::Guess(rule)),
+            None => Some(submit(self.strategy, &mut self.rng, game, &rules)),
+        }
+    }
+}
@@ -0,0 +1,86 @@
+use crate::{
+    PatternParent,
+    helpers::rng::Rng,
+    solver::{
+        advisor::random_pattern,
+        difficulty::uniform_pattern,
+        find_counterexample,
+        rule::{Rule, enumerate_rules},
+    },
+};
+
+use super::{Action, Game, Phase, Player, SeatId};
+
+const KOAN_TRIES: usize = 10_000;
+
+/// A computer master holding a secret rule.
+#[derive(Debug)]
+pub struct BotMaster {
+    rule: Rule,
+    rng: Rng,
+}
+
+impl BotMaster {
+    pub fn new(mut rng: Rng) -> Self {
+        Self {
+            rule: pick_rule(&mut rng),
+            rng,
+        }
+    }
+
+    pub fn with_rule(rule: Rule, rng: Rng) -> Self {
+        Self { rule, rng }
+    }
+
+    /// A koan with the given verdict, or `None` when none turns up, e.g.
+    /// because the rule accepts next to nothing.
+    fn koan(&mut self, valid: bool) -> Option<PatternParent> {
+        (0..KOAN_TRIES)
+            .map(|index| match index % 2 {
+                0 => random_pattern(&mut self.rng),
+                _ => uniform_pattern(&mut self.rng),
+            })
+            .find(|pattern| self.rule.holds(pattern) == valid)
+            .map(|pattern| PatternParent::new(pattern, valid))
+    }
+}
+
+/// A rule of moderate complexity that accepts a fair share of patterns, so
+/// that neither valid nor invalid koans are hard to come by.
+pub fn pick_rule(rng: &mut Rng) -> Rule {
+    let rules: Vec<Rule> = enumerate_rules(4)
+        .into_iter()
+        .filter(|rule| rule.complexity() >= 2)
+        .collect();
+    let samples: Vec<_> = (0..300).map(|_| random_pattern(rng)).collect();
+
+    loop {
+        let rule = rng.pick(&rules);
+        let accepted = samples.iter().filter(|p| rule.holds(p)).count();
+        if (60..=240).contains(&accepted) {
+            return rule.clone();
+        }
+    }
+}
+
+impl Player for BotMaster {
+    fn act(&mut self, game: &Game, _seat: SeatId) -> Option<Action> {
+        match &game.phase {
+            Phase::Opening => {
+                let has_valid = game.koans.iter().any(|koan| koan.valid);
+                self.koan(!has_valid).map(Action::Open)
+            }
+            Phase::Judging { pattern, .. } => Some(Action::Judge(self.rule.holds(pattern))),
+            Phase::Refuting { rule, .. } => {
+                match find_counterexample(&self.rule, rule, &game.koans, &mut self.rng) {
+                    Some(pattern) => Some(Action::Counterexample(PatternParent::new(
+                        pattern,
+                        self.rule.holds(&pattern),
+                    ))),
+                    None => Some(Action::Accept),
+                }
+            }
+            Phase::Turn(_) | Phase::Over { .. } => None,
+        }
+    }
+}
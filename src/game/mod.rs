@@ -0,0 +1,309 @@
+//! Game state and turns, shared by human and computer seats.
+//!
+//! Every seat changes the game only through [`Game::apply`], and everybody
+//! learns about the change through the returned [`Event`]s. A computer seat is
+//! a [`Player`] that picks its [`Action`] from the game state, so the same
+//! interface can later be driven over the network.
+
+pub mod guesser;
+pub mod master;
+
+use std::fmt;
+
+use crate::{Pattern, PatternParent, helpers::rng::Rng, solver::rule::Rule};
+use guesser::{BotGuesser, Strategy};
+use master::BotMaster;
+
+pub type SeatId = usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SeatKind {
+    Human,
+    Bot,
+}
+
+#[derive(Debug, Clone)]
+pub struct Seat {
+    pub name: String,
+    pub kind: SeatKind,
+    pub tokens: usize,
+}
+
+#[derive(Debug, Clone)]
+pub enum Action {
+    /// Master: place an opening koan, until there is a valid and an invalid one.
+    Open(PatternParent),
+    /// Guesser: build a koan for the master to judge, optionally betting on
+    /// the verdict. A correct bet earns a token.
+    Submit {
+        pattern: Pattern,
+        prediction: Option<bool>,
+    },
+    /// Master: judge the submitted koan.
+    Judge(bool),
+    /// Guesser: spend a token guessing the rule.
+    Guess(Rule),
+    /// Master: the guess is right, the game is over.
+    Accept,
+    /// Master: a judged koan on which the guess and the real rule disagree.
+    Counterexample(PatternParent),
+}
+
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// A judged koan was added to the gallery at `index`.
+    Koan {
+        seat: SeatId,
+        index: usize,
+    },
+    Submitted {
+        seat: SeatId,
+    },
+    TokenWon {
+        seat: SeatId,
+    },
+    Guessed {
+        seat: SeatId,
+        rule: Rule,
+    },
+    Refuted {
+        seat: SeatId,
+        index: usize,
+    },
+    Won {
+        seat: SeatId,
+        rule: Rule,
+    },
+}
+
+#[derive(Debug, Clone)]
+pub enum Phase {
+    Opening,
+    /// A guesser's turn to build a koan or guess.
+    Turn(SeatId),
+    Judging {
+        seat: SeatId,
+        pattern: Pattern,
+        prediction: Option<bool>,
+    },
+    Refuting {
+        seat: SeatId,
+        rule: Rule,
+    },
+    Over {
+        winner: SeatId,
+        rule: Rule,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameError {
+    NotYourTurn,
+    WrongAction,
+    NoToken,
+    /// The counterexample agrees with the guessed rule.
+    NotACounterexample,
+    /// An opening koan must have the verdict that is still missing.
+    OpeningVerdictTaken,
+    GameOver,
+}
+
+impl fmt::Display for GameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            GameError::NotYourTurn => "it is not your turn",
+            GameError::WrongAction => "that is not possible right now",
+            GameError::NoToken => "guessing a rule costs a token",
+            GameError::NotACounterexample => "that koan does not disprove the guess",
+            GameError::OpeningVerdictTaken => "the opening needs one valid and one invalid koan",
+            GameError::GameOver => "the game is over",
+        };
+        f.write_str(message)
+    }
+}
+
+impl std::error::Error for GameError {}
+
+/// A seat that decides on its own, like a bot.
+pub trait Player: fmt::Debug {
+    /// Called when `seat` is to act. Returning `None` passes for now.
+    fn act(&mut self, game: &Game, seat: SeatId) -> Option<Action>;
+
+    /// Called with every event, including the ones the player caused.
+    fn observe(&mut self, _game: &Game, _event: &Event) {}
+}
+
+#[derive(Debug, Clone)]
+pub struct Game {
+    pub seats: Vec<Seat>,
+    pub master: SeatId,
+    pub koans: Vec<PatternParent>,
+    pub phase: Phase,
+}
+
+impl Game {
+    pub fn new(seats: Vec<Seat>, master: SeatId) -> Self {
+        Self {
+            seats,
+            master,
+            koans: vec![],
+            phase: Phase::Opening,
+        }
+    }
+
+    /// The seat the game is waiting for.
+    pub fn to_act(&self) -> Option<SeatId> {
+        match &self.phase {
+            Phase::Opening | Phase::Judging { .. } | Phase::Refuting { .. } => Some(self.master),
+            Phase::Turn(seat) => Some(*seat),
+            Phase::Over { .. } => None,
+        }
+    }
+
+    pub fn is_over(&self) -> bool {
+        matches!(self.phase, Phase::Over { .. })
+    }
+
+    pub fn apply(&mut self, seat: SeatId, action: Action) -> Result<Vec<Event>, GameError> {
+        if self.is_over() {
+            return Err(GameError::GameOver);
+        }
+        if self.to_act() != Some(seat) {
+            return Err(GameError::NotYourTurn);
+        }
+
+        let mut events = vec![];
+        match (self.phase.clone(), action) {
+            (Phase::Opening, Action::Open(koan)) => {
+                if self.koans.iter().any(|k| k.valid == koan.valid) {
+                    return Err(GameError::OpeningVerdictTaken);
+                }
+                events.push(self.add_koan(seat, koan));
+                if self.koans.len() == 2 {
+                    self.phase = Phase::Turn(self.next_guesser(self.master));
+                }
+            }
+            (
+                Phase::Turn(_),
+                Action::Submit {
+                    pattern,
+                    prediction,
+                },
+            ) => {
+                self.phase = Phase::Judging {
+                    seat,
+                    pattern,
+                    prediction,
+                };
+                events.push(Event::Submitted { seat });
+            }
+            (Phase::Turn(_), Action::Guess(rule)) => {
+                let tokens = &mut self.seats[seat].tokens;
+                if *tokens == 0 {
+                    return Err(GameError::NoToken);
+                }
+                *tokens -= 1;
+                self.phase = Phase::Refuting {
+                    seat,
+                    rule: rule.clone(),
+                };
+                events.push(Event::Guessed { seat, rule });
+            }
+            (
+                Phase::Judging {
+                    seat: guesser,
+                    pattern,
+                    prediction,
+                },
+                Action::Judge(valid),
+            ) => {
+                events.push(self.add_koan(guesser, PatternParent::new(pattern, valid)));
+                if prediction == Some(valid) {
+                    self.seats[guesser].tokens += 1;
+                    events.push(Event::TokenWon { seat: guesser });
+                }
+                self.phase = Phase::Turn(self.next_guesser(guesser));
+            }
+            (
+                Phase::Refuting {
+                    seat: guesser,
+                    rule,
+                },
+                Action::Accept,
+            ) => {
+                self.phase = Phase::Over {
+                    winner: guesser,
+                    rule: rule.clone(),
+                };
+                events.push(Event::Won {
+                    seat: guesser,
+                    rule,
+                });
+            }
+            (
+                Phase::Refuting {
+                    seat: guesser,
+                    rule,
+                },
+                Action::Counterexample(koan),
+            ) => {
+                if rule.holds(&koan.data) == koan.valid {
+                    return Err(GameError::NotACounterexample);
+                }
+                self.koans.push(koan);
+                events.push(Event::Refuted {
+                    seat: guesser,
+                    index: self.koans.len() - 1,
+                });
+                self.phase = Phase::Turn(self.next_guesser(guesser));
+            }
+            _ => return Err(GameError::WrongAction),
+        }
+        Ok(events)
+    }
+
+    fn add_koan(&mut self, seat: SeatId, koan: PatternParent) -> Event {
+        self.koans.push(koan);
+        Event::Koan {
+            seat,
+            index: self.koans.len() - 1,
+        }
+    }
+
+    /// The guesser after `seat`, going round the table and skipping the master.
+    fn next_guesser(&self, seat: SeatId) -> SeatId {
+        (1..=self.seats.len())
+            .map(|offset| (seat + offset) % self.seats.len())
+            .find(|candidate| *candidate != self.master)
+            .unwrap_or(seat)
+    }
+}
+
+/// Plays a computer master holding `rule` against one computer guesser and
+/// returns how many turns the guesser needed, or `None` if it did not find
+/// the rule within `max_turns`.
+pub fn simulate(rule: Rule, strategy: Strategy, rng: &mut Rng, max_turns: usize) -> Option<usize> {
+    let seat = |name: &str| Seat {
+        name: name.to_string(),
+        kind: SeatKind::Bot,
+        tokens: 0,
+    };
+    let mut game = Game::new(vec![seat("master"), seat("guesser")], 0);
+    let mut players: [Box<dyn Player>; 2] = [
+        Box::new(BotMaster::with_rule(rule, Rng::new(rng.next_u64()))),
+        Box::new(BotGuesser::new(strategy, Rng::new(rng.next_u64()))),
+    ];
+
+    let mut turns = 0;
+    while let Some(seat) = game.to_act() {
+        if seat == 1 {
+            turns += 1;
+            if turns > max_turns {
+                return None;
+            }
+        }
+        let action = players[seat].act(&game, seat)?;
+        game.apply(seat, action).ok()?;
+    }
+    Some(turns)
+}
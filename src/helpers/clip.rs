@@ -0,0 +1,68 @@
+//! The piece clipboard. A clip is a rectangle of cells copied from a pattern,
+//! from a single cell up to the whole grid, empty cells included, so pasting
+//! it gives the same picture.
+
+use crate::{HEIGHT, Pattern, Piece, WIDTH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Clip {
+    /// Rows of cells, all the same length.
+    cells: Vec<Vec<Option<Piece>>>,
+}
+
+impl Clip {
+    /// Copies the rectangle with the corners `from` and `to`, in any order.
+    pub fn copy(pattern: &Pattern, from: (usize, usize), to: (usize, usize)) -> Self {
+        let ((left, top), (right, bottom)) = rectangle(from, to);
+        let cells = (top..=bottom)
+            .map(|row| (left..=right).map(|col| pattern.get((col, row))).collect())
+            .collect();
+        Self { cells }
+    }
+
+    pub fn whole(pattern: &Pattern) -> Self {
+        Self::copy(pattern, (0, 0), (WIDTH - 1, HEIGHT - 1))
+    }
+
+    pub fn width(&self) -> usize {
+        self.cells.first().map_or(0, Vec::len)
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// Number of pieces in the clip.
+    pub fn pieces(&self) -> usize {
+        self.cells.iter().flatten().flatten().count()
+    }
+
+    /// Pastes the clip with its top left cell at `at`, moved up and left as
+    /// far as it takes to fit on the grid. A whole pattern always lands on
+    /// the whole grid.
+    pub fn paste(&self, pattern: &mut Pattern, at: (usize, usize)) {
+        let left = at.0.min(WIDTH - self.width());
+        let top = at.1.min(HEIGHT - self.height());
+        for (row, cells) in self.cells.iter().enumerate() {
+            for (col, cell) in cells.iter().enumerate() {
+                let pos = (left + col, top + row);
+                match cell {
+                    Some(piece) => pattern.set(pos, *piece),
+                    None => pattern.clear(pos),
+                };
+            }
+        }
+    }
+}
+
+/// The top left and bottom right corners of the rectangle with the corners
+/// `a` and `b`.
+pub fn rectangle(a: (usize, usize), b: (usize, usize)) -> ((usize, usize), (usize, usize)) {
+    ((a.0.min(b.0), a.1.min(b.1)), (a.0.max(b.0), a.1.max(b.1)))
+}
+
+/// Whether `pos` is in the rectangle with the corners `a` and `b`.
+pub fn in_rectangle(pos: (usize, usize), a: (usize, usize), b: (usize, usize)) -> bool {
+    let ((left, top), (right, bottom)) = rectangle(a, b);
+    (left..=right).contains(&pos.0) && (top..=bottom).contains(&pos.1)
+}
@@ -0,0 +1,254 @@
+//! The `:` command palette. Every command either does what a key does, or
+//! takes an argument no key can, like `goto 17` or `save game.zendo`. The vim
+//! keys are commands too, see `helpers::vim`.
+
+use crossterm::event::KeyCode;
+
+use crate::helpers::gallery::{Filter, MAX_GALLERY_COLUMNS, MAX_GALLERY_ROWS};
+use crate::helpers::transform::Transform;
+use crate::solver::rule::Rule;
+use crate::ui::palette::Attribute;
+use crate::{HEIGHT, Keymap, Theme, WIDTH};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Presses a key, for the commands that only name a key binding.
+    Key(KeyCode),
+    Goto(usize),
+    Filter(Filter),
+    /// Saves the game, to another file if one is given.
+    Save(Option<String>),
+    Theme(Theme),
+    Rows(usize),
+    Columns(usize),
+    /// Clears the editor.
+    New,
+    Name(String),
+    /// Guesses the rule in a game, otherwise checks and rates it.
+    Rule(Rule),
+    /// Turns the rule assistant on or off, only the master can in a game.
+    Assistant(bool),
+    /// Whether rotations and mirrors count as repeats with the assistant off.
+    SymmetricRepeats(bool),
+    Keymap(Keymap),
+    /// Moves the cursor by columns and rows, stopping at the edges.
+    Move(isize, isize),
+    /// Removes the piece under the cursor, or the selected ones.
+    Clear,
+    /// Copies the selection, or the piece under the cursor, for `Put`.
+    Yank,
+    YankKoan,
+    Put,
+    /// Starts or ends a block selection.
+    Select,
+    /// Sets the palette piece, and the piece under the cursor.
+    Set(Vec<Attribute>),
+    /// Changes the whole editor koan, or the selected one.
+    Transform(Transform),
+    /// Takes back the last piece change.
+    Undo,
+    Redo,
+}
+
+/// Commands that press a key, with the key.
+const KEY_COMMANDS: &[(&str, KeyCode)] = &[
+    ("quit", KeyCode::Char('q')),
+    ("q", KeyCode::Char('q')),
+    ("help", KeyCode::Char('h')),
+    ("log", KeyCode::Char('L')),
+    ("rules", KeyCode::Char('?')),
+    ("suggest", KeyCode::Char('n')),
+    ("annotate", KeyCode::Char('t')),
+    ("edit", KeyCode::Char('e')),
+    ("load", KeyCode::Char('l')),
+    ("copy", KeyCode::Char('C')),
+    ("delete", KeyCode::Delete),
+    ("undo", KeyCode::Char('u')),
+    ("select", KeyCode::Char('b')),
+    ("yank", KeyCode::Char('k')),
+    ("yank-koan", KeyCode::Char('K')),
+    ("put", KeyCode::Char('p')),
+    ("carry", KeyCode::Char('z')),
+    ("stamp", KeyCode::Char('f')),
+    ("accept", KeyCode::Char('Y')),
+    ("bot-master", KeyCode::Char('M')),
+    ("bots", KeyCode::Char('B')),
+    ("lobby", KeyCode::Char('J')),
+    ("replay", KeyCode::Char('F')),
+    ("compact", KeyCode::Char('g')),
+    ("ascii", KeyCode::Char('G')),
+];
+
+/// Commands that are not a key, and the arguments that can be completed.
+const ARGUMENT_COMMANDS: &[(&str, &[&str])] = &[
+    ("goto", &[]),
+    ("filter", &["all", "valid", "invalid", "tag"]),
+    ("save", &[]),
+    ("w", &[]),
+    ("theme", &["color", "mono"]),
+    ("keys", &["default", "vim"]),
+    ("rotate", &[]),
+    ("mirror", &["horizontal", "vertical"]),
+    ("shift", &["left", "right", "up", "down"]),
+    ("colors", &[]),
+    ("swap", &["red", "blue", "yellow"]),
+    ("grow", &[]),
+    ("shrink", &[]),
+    (
+        "set",
+        &[
+            "red", "blue", "yellow", "small", "medium", "large", "straight", "angled",
+        ],
+    ),
+    ("rows", &[]),
+    ("columns", &[]),
+    ("new", &[]),
+    ("name", &[]),
+    ("rule", &[]),
+    ("assistant", &["on", "off"]),
+    ("repeats", &["exact", "symmetric"]),
+];
+
+pub fn parse_command(text: &str) -> Result<Command, String> {
+    let text = text.trim();
+    let (name, argument) = text
+        .split_once(' ')
+        .map(|(name, argument)| (name, argument.trim()))
+        .unwrap_or((text, ""));
+
+    if let Some((_, key)) = KEY_COMMANDS.iter().find(|(command, _)| *command == name) {
+        return match argument {
+            "" => Ok(Command::Key(*key)),
+            _ => Err(format!("{name} takes no argument")),
+        };
+    }
+
+    let needs = |what: &str| format!("{name} needs {what}");
+    match name {
+        "goto" => argument
+            .parse()
+            .map(Command::Goto)
+            .map_err(|_| needs("a koan number")),
+        "filter" => match argument.split_once(' ') {
+            None if argument == "all" || argument.is_empty() => Ok(Command::Filter(Filter::All)),
+            None if argument == "valid" => Ok(Command::Filter(Filter::Valid)),
+            None if argument == "invalid" => Ok(Command::Filter(Filter::Invalid)),
+            Some(("tag", tag)) => Ok(Command::Filter(Filter::Tag(tag.trim().to_string()))),
+            _ => Err(needs("all, valid, invalid or tag NAME")),
+        },
+        "save" | "w" => Ok(Command::Save(
+            Some(argument.to_string()).filter(|path| !path.is_empty()),
+        )),
+        "theme" => match argument {
+            "color" => Ok(Command::Theme(Theme::Color)),
+            "mono" => Ok(Command::Theme(Theme::Mono)),
+            _ => Err(needs("color or mono")),
+        },
+        "set" if !argument.is_empty() => argument
+            .split_whitespace()
+            .map(|name| Attribute::parse(name).ok_or_else(|| format!("unknown attribute {name}")))
+            .collect::<Result<_, _>>()
+            .map(Command::Set),
+        "set" => Err(needs("colors, sizes or types, like red large")),
+        "rotate" if argument.is_empty() => Ok(Command::Transform(Transform::Rotate(1))),
+        "rotate" => argument
+            .parse()
+            .map(|turns| Command::Transform(Transform::Rotate(turns)))
+            .map_err(|_| needs("a number of quarter turns")),
+        "mirror" => match argument {
+            "horizontal" | "h" => Ok(Command::Transform(Transform::MirrorHorizontal)),
+            "vertical" | "v" => Ok(Command::Transform(Transform::MirrorVertical)),
+            _ => Err(needs("horizontal or vertical")),
+        },
+        "shift" => {
+            let (direction, steps) = argument.split_once(' ').unwrap_or((argument, "1"));
+            let steps: isize = steps
+                .trim()
+                .parse()
+                .map_err(|_| needs("a number of cells"))?;
+            // Whole turns around the grid change nothing, and huge counts
+            // would overflow.
+            let columns = steps.rem_euclid(WIDTH as isize);
+            let rows = steps.rem_euclid(HEIGHT as isize);
+            let (columns, rows) = match direction {
+                "left" => (-columns, 0),
+                "right" => (columns, 0),
+                "up" => (0, -rows),
+                "down" => (0, rows),
+                _ => return Err(needs("left, right, up or down")),
+            };
+            Ok(Command::Transform(Transform::Shift(columns, rows)))
+        }
+        "colors" => Ok(Command::Transform(Transform::CycleColors)),
+        "swap" => match argument
+            .split_whitespace()
+            .map(Attribute::parse)
+            .collect::<Vec<_>>()
+            .as_slice()
+        {
+            [Some(Attribute::Color(a)), Some(Attribute::Color(b))] => {
+                Ok(Command::Transform(Transform::SwapColors(*a, *b)))
+            }
+            _ => Err(needs("two colors, like red blue")),
+        },
+        "grow" => Ok(Command::Transform(Transform::Grow)),
+        "shrink" => Ok(Command::Transform(Transform::Shrink)),
+        "keys" => Keymap::parse(argument)
+            .map(Command::Keymap)
+            .ok_or_else(|| needs("default or vim")),
+        "rows" => parse_count(argument, MAX_GALLERY_ROWS)
+            .map(Command::Rows)
+            .ok_or_else(|| needs(&format!("a number from 1 to {MAX_GALLERY_ROWS}"))),
+        "columns" => parse_count(argument, MAX_GALLERY_COLUMNS)
+            .map(Command::Columns)
+            .ok_or_else(|| needs(&format!("a number from 1 to {MAX_GALLERY_COLUMNS}"))),
+        // The solver, the rules and the notation all work on one grid size.
+        "new" if argument.is_empty() => Ok(Command::New),
+        "new" => Err(format!(
+            "koans are always {WIDTH}x{HEIGHT}, new takes no size"
+        )),
+        "name" if !argument.is_empty() => Ok(Command::Name(argument.to_string())),
+        "name" => Err(needs("a name")),
+        "rule" => argument
+            .parse()
+            .map(Command::Rule)
+            .map_err(|err| format!("{err}")),
+        "assistant" => match argument {
+            "on" => Ok(Command::Assistant(true)),
+            "off" => Ok(Command::Assistant(false)),
+            _ => Err(needs("on or off")),
+        },
+        "repeats" => match argument {
+            "exact" => Ok(Command::SymmetricRepeats(false)),
+            "symmetric" => Ok(Command::SymmetricRepeats(true)),
+            _ => Err(needs("exact or symmetric")),
+        },
+        "" => Err("type a command, Tab lists them".to_string()),
+        _ => Err(format!("unknown command {name}")),
+    }
+}
+
+fn parse_count(text: &str, max: usize) -> Option<usize> {
+    text.parse().ok().filter(|count| (1..=max).contains(count))
+}
+
+/// Completions for the palette: command names for the first word, and the
+/// arguments some commands know for the second.
+pub fn complete_command(text: &str) -> Vec<String> {
+    match text.split_once(' ') {
+        None => KEY_COMMANDS
+            .iter()
+            .map(|(name, _)| *name)
+            .chain(ARGUMENT_COMMANDS.iter().map(|(name, _)| *name))
+            .filter(|name| name.starts_with(text))
+            .map(str::to_string)
+            .collect(),
+        Some((name, argument)) => ARGUMENT_COMMANDS
+            .iter()
+            .filter(|(command, _)| *command == name)
+            .flat_map(|(_, arguments)| arguments.iter())
+            .filter(|candidate| candidate.starts_with(argument))
+            .map(|candidate| format!("{name} {candidate}"))
+            .collect(),
+    }
+}
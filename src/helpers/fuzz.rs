@@ -0,0 +1,363 @@
+use std::panic::{AssertUnwindSafe, catch_unwind};
+use std::time::Duration;
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{Terminal, backend::TestBackend};
+
+use crate::helpers::gallery::{Filter, check_viewport, select_pattern, set_filter};
+use crate::helpers::game_handler::run_bots;
+use crate::helpers::key_handler::on_key_event;
+use crate::helpers::rng::Rng;
+use crate::solver::difficulty::uniform_pattern;
+use crate::{App, Keymap, PatternParent, Prompt, PromptKind, Screen};
+
+/// Keys the fuzzer presses. The clipboard is left out, it would write escape
+/// codes. Saves and exports are in, the fuzzer turns off writing files.
+const KEYS: &[KeyCode] = &[
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+    KeyCode::Enter,
+    KeyCode::Backspace,
+    KeyCode::Esc,
+    KeyCode::Char('q'),
+    KeyCode::Char('r'),
+    KeyCode::Char('c'),
+    KeyCode::Char('s'),
+    KeyCode::Char('i'),
+    KeyCode::Char('m'),
+    KeyCode::Char('e'),
+    KeyCode::Char('v'),
+    KeyCode::Char('a'),
+    KeyCode::Char('d'),
+    KeyCode::Char('W'),
+    KeyCode::Char('A'),
+    KeyCode::Char('S'),
+    KeyCode::Char('D'),
+    KeyCode::Char('<'),
+    KeyCode::Char('>'),
+    KeyCode::Char(','),
+    KeyCode::Char('.'),
+    KeyCode::Char(':'),
+    KeyCode::Char('k'),
+    KeyCode::Char('p'),
+    KeyCode::Char('3'),
+    KeyCode::Char('b'),
+    KeyCode::Char('K'),
+    KeyCode::Char('z'),
+    KeyCode::Char('1'),
+    KeyCode::Char('6'),
+    KeyCode::Char('8'),
+    KeyCode::Char('f'),
+    KeyCode::Char('w'),
+    KeyCode::Char('H'),
+    KeyCode::Char('P'),
+    KeyCode::Char('g'),
+    KeyCode::Char('G'),
+    KeyCode::Char('n'),
+    KeyCode::Char('?'),
+    KeyCode::Char('M'),
+    KeyCode::Char('J'),
+    KeyCode::Char('F'),
+    KeyCode::Char(' '),
+    KeyCode::Char('B'),
+    KeyCode::Char('Y'),
+    KeyCode::Char('L'),
+    KeyCode::Char('t'),
+    KeyCode::Char('R'),
+    KeyCode::Char('N'),
+    KeyCode::Char('h'),
+    KeyCode::Delete,
+    KeyCode::Char('u'),
+    KeyCode::Char('['),
+    KeyCode::Char(']'),
+    KeyCode::Char('C'),
+    KeyCode::Char('l'),
+    KeyCode::Tab,
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::Char('j'),
+    KeyCode::Char('x'),
+    KeyCode::Char('X'),
+    KeyCode::Char('o'),
+    KeyCode::Char('O'),
+];
+
+/// Keys the fuzzer presses with Ctrl held.
+const CTRL_KEYS: &[KeyCode] = &[
+    KeyCode::Char('s'),
+    KeyCode::Char('z'),
+    KeyCode::Char('y'),
+    KeyCode::Char('r'),
+    KeyCode::Up,
+    KeyCode::Down,
+    KeyCode::Left,
+    KeyCode::Right,
+];
+
+/// Palette commands the fuzzer types after `:`, with arguments at the edges
+/// of what they take. Rules are left out, they take a while to rate.
+const COMMANDS: &[&str] = &[
+    "save fuzz.zendo",
+    "w",
+    "goto 0",
+    "goto 18446744073709551615",
+    "filter valid",
+    "filter tag x",
+    "filter all",
+    "theme mono",
+    "rows 1",
+    "columns 99",
+    "keys vim",
+    "keys default",
+    "rotate 3",
+    "mirror vertical",
+    "shift right 9223372036854775807",
+    "shift up -9223372036854775808",
+    "colors",
+    "swap red yellow",
+    "grow",
+    "shrink",
+    "set blue small angled",
+    "new",
+    "name Fuzz",
+    "assistant off",
+    "assistant on",
+    "repeats symmetric",
+    "yank-koan",
+    "put",
+    "undo",
+];
+
+/// What one fuzzing run picks its input from.
+struct Input<'a> {
+    keys: &'a [KeyCode],
+    ctrl_keys: &'a [KeyCode],
+    commands: &'a [&'a str],
+}
+
+/// Keys that move around the gallery or change its shape, for `fuzz_gallery`.
+const GALLERY_KEYS: &[KeyCode] = &[
+    KeyCode::Char('W'),
+    KeyCode::Char('A'),
+    KeyCode::Char('S'),
+    KeyCode::Char('D'),
+    KeyCode::Char('a'),
+    KeyCode::Char('d'),
+    KeyCode::Char('<'),
+    KeyCode::Char('>'),
+    KeyCode::Char(','),
+    KeyCode::Char('.'),
+    KeyCode::PageUp,
+    KeyCode::PageDown,
+    KeyCode::Home,
+    KeyCode::End,
+    KeyCode::Char('C'),
+    KeyCode::Char('['),
+    KeyCode::Char(']'),
+    KeyCode::Delete,
+    KeyCode::Char('u'),
+];
+
+/// A key sequence that made the app panic or left the gallery showing the
+/// wrong koans.
+#[derive(Debug)]
+pub struct Crash {
+    pub keys: Vec<KeyEvent>,
+    pub message: String,
+}
+
+impl Crash {
+    /// The keys for a report, Ctrl ones marked.
+    pub fn pressed(&self) -> String {
+        let keys: Vec<String> = self
+            .keys
+            .iter()
+            .map(|key| match key.modifiers.contains(KeyModifiers::CONTROL) {
+                true => format!("Ctrl-{:?}", key.code),
+                false => format!("{:?}", key.code),
+            })
+            .collect();
+        format!("[{}]", keys.join(", "))
+    }
+}
+
+/// Feeds `steps` random keys into `on_key_event` and draws the app after
+/// each one, on terminals of a few sizes. Some keys are pressed with Ctrl,
+/// and some steps type a whole palette command. Quitting is ignored, so the
+/// gallery keeps growing, and nothing is written to disk. Odd seeds press
+/// them in the vim preset.
+pub fn fuzz(steps: usize, seed: u64) -> Result<(), Crash> {
+    let mut app = App::new();
+    app.write_files = false;
+    app.rng = Rng::new(seed);
+    app.think_delay = Duration::ZERO;
+    if seed % 2 == 1 {
+        app.keymap = Keymap::Vim;
+    }
+    let input = Input {
+        keys: KEYS,
+        ctrl_keys: CTRL_KEYS,
+        commands: COMMANDS,
+    };
+    press_keys(&mut app, &input, steps, seed)
+}
+
+/// Like `fuzz`, with a gallery of up to a hundred random koans, sometimes
+/// filtered, and only the keys that move around it, to check that the
+/// selection never leaves the shown koans.
+pub fn fuzz_gallery(steps: usize, seed: u64) -> Result<(), Crash> {
+    let mut rng = Rng::new(seed);
+    let mut app = App::new();
+    app.write_files = false;
+    app.confirmations.clear();
+    for _ in 0..rng.below(100) {
+        let pattern = uniform_pattern(&mut rng);
+        app.patterns
+            .push(PatternParent::new(pattern, rng.below(2) == 0));
+    }
+    let selected = rng.below(app.patterns.len() + 1);
+    select_pattern(&mut app, selected);
+    let filter = [Filter::All, Filter::Valid, Filter::Invalid][rng.below(3)].clone();
+    set_filter(&mut app, filter);
+    let input = Input {
+        keys: GALLERY_KEYS,
+        ctrl_keys: &[],
+        commands: &[],
+    };
+    press_keys(&mut app, &input, steps, rng.next_u64())
+}
+
+fn press_keys(app: &mut App, input: &Input, steps: usize, seed: u64) -> Result<(), Crash> {
+    let mut rng = Rng::new(seed);
+    let mut pressed = vec![];
+
+    for step in 0..steps {
+        let command = match rng.below(20) {
+            0 if !input.commands.is_empty() => Some(*rng.pick(input.commands)),
+            _ => None,
+        };
+        let key = match rng.below(10) {
+            0 if !input.ctrl_keys.is_empty() => {
+                KeyEvent::new(*rng.pick(input.ctrl_keys), KeyModifiers::CONTROL)
+            }
+            _ => KeyEvent::new(*rng.pick(input.keys), KeyModifiers::NONE),
+        };
+        let size = [(160, 50), (80, 24), (40, 12)][step % 3];
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            match command {
+                Some(command) => type_command(app, command, &mut pressed),
+                None => {
+                    pressed.push(key);
+                    on_key_event(app, key);
+                }
+            }
+            run_bots(app);
+            let mut terminal = Terminal::new(TestBackend::new(size.0, size.1)).unwrap();
+            terminal.draw(|frame| app.render(frame)).unwrap();
+        }));
+        if let Err(panic) = result {
+            let message = panic
+                .downcast_ref::<String>()
+                .cloned()
+                .or_else(|| panic.downcast_ref::<&str>().map(|s| s.to_string()))
+                .unwrap_or_default();
+            return Err(Crash {
+                keys: pressed,
+                message: format!("panicked: {message}"),
+            });
+        }
+        if let Err(message) = check_viewport(app) {
+            return Err(Crash {
+                keys: pressed,
+                message,
+            });
+        }
+
+        app.running = true;
+    }
+    Ok(())
+}
+
+/// Types `:`, the command and Enter. A prompt that is already open is
+/// cancelled first, so the command is not typed after what is in it. Nothing
+/// is typed unless `:` opened a fresh command prompt, over another popup the
+/// letters would be keys.
+fn type_command(app: &mut App, command: &str, pressed: &mut Vec<KeyEvent>) {
+    let mut press = |app: &mut App, code: KeyCode| {
+        let key = KeyEvent::new(code, KeyModifiers::NONE);
+        pressed.push(key);
+        on_key_event(app, key);
+    };
+    if matches!(app.screen(), Screen::Prompt(_)) {
+        press(app, KeyCode::Esc);
+    }
+    let screens = app.screens.len();
+    press(app, KeyCode::Char(':'));
+    let opened = app.screens.len() == screens + 1
+        && matches!(
+            app.screen(),
+            Screen::Prompt(Prompt {
+                kind: PromptKind::Command,
+                input,
+            }) if input.text().is_empty()
+        );
+    if !opened {
+        return;
+    }
+    for code in command.chars().map(KeyCode::Char).chain([KeyCode::Enter]) {
+        press(app, code);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Few steps, debug builds draw slowly. Odd seeds use the vim keys.
+    const STEPS: usize = 300;
+
+    /// Fewer still for the gallery, whose pages hold up to 64 koans.
+    const GALLERY_STEPS: usize = 60;
+
+    fn files() -> Vec<std::path::PathBuf> {
+        let mut files: Vec<_> = std::fs::read_dir(".")
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        files.sort();
+        files
+    }
+
+    #[test]
+    fn fuzz_fixed_seeds() {
+        let before = files();
+        for seed in 0..4 {
+            if let Err(crash) = fuzz(STEPS, seed) {
+                panic!(
+                    "seed {seed}: {}\nafter keys {}",
+                    crash.message,
+                    crash.pressed()
+                );
+            }
+        }
+        assert_eq!(files(), before, "fuzzing wrote files");
+    }
+
+    #[test]
+    fn fuzz_gallery_fixed_seeds() {
+        for seed in 0..4 {
+            if let Err(crash) = fuzz_gallery(GALLERY_STEPS, seed) {
+                panic!(
+                    "seed {seed}: {}\nafter keys {}",
+                    crash.message,
+                    crash.pressed()
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,338 @@
+//! Moving around the gallery. The koans the filter shows are laid out in rows
+//! of `patterns_per_row`, and the gallery shows `pattern_rows` whole rows of
+//! them, from `render_start_index` to `render_end_index`. Those two count
+//! shown koans, `selected_pattern_index` counts all of them. Every move
+//! selects a koan and then scrolls just far enough to show it.
+
+use std::fmt;
+
+use crate::{App, PatternParent};
+
+/// Bigger galleries do not fit a terminal and make the layout slow to solve.
+pub const MAX_GALLERY_ROWS: usize = 8;
+pub const MAX_GALLERY_COLUMNS: usize = 8;
+
+/// A direction on the gallery grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    Up,
+    Down,
+    Left,
+    Right,
+}
+
+/// Which koans the gallery shows.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum Filter {
+    #[default]
+    All,
+    Valid,
+    Invalid,
+    Tag(String),
+}
+
+impl Filter {
+    pub fn shows(&self, pattern: &PatternParent) -> bool {
+        match self {
+            Filter::All => true,
+            Filter::Valid => pattern.valid,
+            Filter::Invalid => !pattern.valid,
+            Filter::Tag(tag) => pattern.annotation.tags.contains(tag),
+        }
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Filter::All => f.write_str("all"),
+            Filter::Valid => f.write_str("valid"),
+            Filter::Invalid => f.write_str("invalid"),
+            Filter::Tag(tag) => write!(f, "tag {tag}"),
+        }
+    }
+}
+
+/// Gallery indexes of the koans the filter shows, in order.
+pub fn shown_patterns(app: &App) -> Vec<usize> {
+    app.patterns
+        .iter()
+        .enumerate()
+        .filter(|(_, pattern)| app.filter.shows(pattern))
+        .map(|(index, _)| index)
+        .collect()
+}
+
+/// Selects a saved koan and scrolls the gallery so it is shown. Indexes past
+/// the end select the last koan. A koan the filter hides turns the filter
+/// off.
+pub fn select_pattern(app: &mut App, index: usize) {
+    let index = index.min(app.patterns.len().saturating_sub(1));
+    if app
+        .patterns
+        .get(index)
+        .is_some_and(|pattern| !app.filter.shows(pattern))
+    {
+        set_filter(app, Filter::All);
+    }
+    app.pattern_index = index;
+    app.selected_pattern_index = index;
+    scroll_to_selection(app);
+}
+
+/// Selects the newest koan and scrolls the gallery to it.
+pub fn select_last_pattern(app: &mut App) {
+    select_pattern(app, usize::MAX);
+}
+
+pub fn set_filter(app: &mut App, filter: Filter) {
+    if filter != Filter::All {
+        app.notifications.info(format!("Showing {filter} koans"));
+    } else if app.filter != Filter::All {
+        app.notifications.info("Showing all koans");
+    }
+    app.filter = filter;
+    scroll_to_selection(app);
+}
+
+/// Moves the selection one cell. It stops at the edges of the grid, except
+/// that moving down into a shorter last row selects its last koan.
+pub fn step_selection(app: &mut App, step: Step) {
+    let shown = shown_patterns(app);
+    let columns = app.patterns_per_row;
+    let slot = selected_slot(app, &shown);
+    let last = shown.len().saturating_sub(1);
+    let target = match step {
+        Step::Left if !slot.is_multiple_of(columns) => slot - 1,
+        Step::Right if !(slot + 1).is_multiple_of(columns) && slot < last => slot + 1,
+        Step::Up => slot.checked_sub(columns).unwrap_or(slot),
+        Step::Down if slot / columns < last / columns => (slot + columns).min(last),
+        _ => slot,
+    };
+    select_slot(app, &shown, target);
+}
+
+/// Moves the selection a whole page of rows, `-1` for up and `1` for down.
+pub fn page_selection(app: &mut App, pages: isize) {
+    let shown = shown_patterns(app);
+    let page = (app.patterns_per_row * app.pattern_rows) as isize;
+    let target = selected_slot(app, &shown) as isize + pages * page;
+    select_slot(app, &shown, target.max(0) as usize);
+}
+
+/// Scrolls the gallery by whole rows, taking the selection along when it
+/// would scroll out of view.
+pub fn scroll_rows(app: &mut App, rows: isize) {
+    let shown = shown_patterns(app);
+    let columns = app.patterns_per_row;
+    let first_row = (app.render_start_index / columns) as isize + rows;
+    app.render_start_index = first_row.max(0) as usize * columns;
+    clamp_viewport(app, shown.len());
+
+    let slot = selected_slot(app, &shown).clamp(app.render_start_index, app.render_end_index);
+    if let Some(index) = shown.get(slot) {
+        app.pattern_index = *index;
+        app.selected_pattern_index = *index;
+    }
+}
+
+/// Brings the selection back into view after the grid changed shape, e.g.
+/// with `<` or `,`, or after the selected koan was hidden. With every koan
+/// hidden the filter is turned off.
+pub fn scroll_to_selection(app: &mut App) {
+    let mut shown = shown_patterns(app);
+    if shown.is_empty() && app.filter != Filter::All {
+        app.notifications
+            .info(format!("No koan is {}, showing all koans", app.filter));
+        app.filter = Filter::All;
+        shown = shown_patterns(app);
+    }
+    let slot = selected_slot(app, &shown);
+    if let Some(index) = shown.get(slot) {
+        app.pattern_index = *index;
+        app.selected_pattern_index = *index;
+    } else {
+        app.selected_pattern_index = 0;
+    }
+
+    let columns = app.patterns_per_row;
+    let selected_row = slot / columns;
+    let first_row = (app.render_start_index / columns)
+        .min(selected_row)
+        .max((selected_row + 1).saturating_sub(app.pattern_rows));
+    app.render_start_index = first_row * columns;
+    clamp_viewport(app, shown.len());
+}
+
+/// Where the selected koan is among the shown ones. A hidden koan counts as
+/// the next shown one.
+fn selected_slot(app: &App, shown: &[usize]) -> usize {
+    shown
+        .iter()
+        .position(|index| *index >= app.selected_pattern_index)
+        .unwrap_or(shown.len().saturating_sub(1))
+}
+
+fn select_slot(app: &mut App, shown: &[usize], slot: usize) {
+    if let Some(index) = shown.get(slot.min(shown.len().saturating_sub(1))) {
+        app.pattern_index = *index;
+        app.selected_pattern_index = *index;
+    }
+    scroll_to_selection(app);
+}
+
+/// Keeps the shown rows on the grid, without empty rows below the last koan
+/// while there are koans above the first shown row.
+fn clamp_viewport(app: &mut App, shown: usize) {
+    let columns = app.patterns_per_row;
+    let rows = shown.div_ceil(columns);
+    let first_row = (app.render_start_index / columns).min(rows.saturating_sub(app.pattern_rows));
+    app.render_start_index = first_row * columns;
+    app.render_end_index = (app.render_start_index + columns * app.pattern_rows)
+        .min(shown)
+        .saturating_sub(1);
+}
+
+/// What is wrong with the gallery state, if anything. The fuzzer checks this
+/// after every key.
+pub fn check_viewport(app: &App) -> Result<(), String> {
+    let shown = shown_patterns(app);
+    let columns = app.patterns_per_row;
+    let page = columns * app.pattern_rows;
+    let (start, end, selected) = (
+        app.render_start_index,
+        app.render_end_index,
+        app.selected_pattern_index,
+    );
+    if app.patterns.is_empty() {
+        return match (start, end, selected) {
+            (0, 0, 0) => Ok(()),
+            _ => Err(format!(
+                "empty gallery shows {start}..={end} with {selected} selected"
+            )),
+        };
+    }
+    if shown.is_empty() {
+        return Err(format!("the filter {} hides every koan", app.filter));
+    }
+    let Some(slot) = shown.iter().position(|index| *index == selected) else {
+        return Err(format!("koan {selected} is selected but hidden"));
+    };
+    if !start.is_multiple_of(columns) {
+        return Err(format!("the gallery starts mid row at {start}"));
+    }
+    if end >= shown.len() || end < start || end - start >= page {
+        return Err(format!(
+            "the gallery shows {start}..={end} of {} koans, {page} fit",
+            shown.len()
+        ));
+    }
+    if !(start..=end).contains(&slot) {
+        return Err(format!(
+            "koan {selected} is selected but {start}..={end} are shown"
+        ));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Pattern;
+    use crate::helpers::rng::Rng;
+
+    /// A gallery of up to forty koans, some valid and some tagged, with a
+    /// random shape and selection.
+    fn random_app(rng: &mut Rng) -> App {
+        let mut app = App::new();
+        for _ in 0..rng.below(41) {
+            let mut koan = PatternParent::new(Pattern::default(), rng.below(2) == 0);
+            if rng.below(3) == 0 {
+                koan.annotation.tags.push("odd".to_string());
+            }
+            app.patterns.push(koan);
+        }
+        app.patterns_per_row = 1 + rng.below(MAX_GALLERY_COLUMNS);
+        app.pattern_rows = 1 + rng.below(MAX_GALLERY_ROWS);
+        let selected = rng.below(app.patterns.len() + 2);
+        select_pattern(&mut app, selected);
+        app
+    }
+
+    fn random_filter(rng: &mut Rng) -> Filter {
+        match rng.below(5) {
+            0 => Filter::Valid,
+            1 => Filter::Invalid,
+            2 => Filter::Tag("odd".to_string()),
+            3 => Filter::Tag("missing".to_string()),
+            _ => Filter::All,
+        }
+    }
+
+    /// One random move, like the keys and commands that move around.
+    fn random_move(app: &mut App, rng: &mut Rng) {
+        match rng.below(9) {
+            0 => {
+                let step = *rng.pick(&[Step::Up, Step::Down, Step::Left, Step::Right]);
+                step_selection(app, step);
+            }
+            1 => page_selection(app, *rng.pick(&[-1, 1])),
+            2 => scroll_rows(app, *rng.pick(&[-1, 1])),
+            3 => {
+                let index = rng.below(app.patterns.len() + 2);
+                select_pattern(app, index);
+            }
+            4 => select_last_pattern(app),
+            5 => set_filter(app, random_filter(rng)),
+            6 => {
+                app.patterns_per_row = 1 + rng.below(MAX_GALLERY_COLUMNS);
+                scroll_to_selection(app);
+            }
+            7 => {
+                app.pattern_rows = 1 + rng.below(MAX_GALLERY_ROWS);
+                scroll_to_selection(app);
+            }
+            _ => {
+                // the selected koan changes its verdict, e.g. with `i`
+                if let Some(koan) = app.patterns.get_mut(app.selected_pattern_index) {
+                    koan.valid = !koan.valid;
+                }
+                scroll_to_selection(app);
+            }
+        }
+    }
+
+    #[test]
+    fn random_navigation_keeps_the_selection_shown() {
+        for seed in 0..200 {
+            let mut rng = Rng::new(seed);
+            let mut app = random_app(&mut rng);
+            for step in 0..200 {
+                random_move(&mut app, &mut rng);
+                if let Err(message) = check_viewport(&app) {
+                    panic!("seed {seed}, step {step}: {message}");
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn steps_undo_each_other() {
+        let opposites = [
+            (Step::Left, Step::Right),
+            (Step::Right, Step::Left),
+            (Step::Up, Step::Down),
+        ];
+        for seed in 0..200 {
+            let mut rng = Rng::new(seed);
+            let mut app = random_app(&mut rng);
+            let (step, back) = *rng.pick(&opposites);
+            let start = app.selected_pattern_index;
+            step_selection(&mut app, step);
+            if app.selected_pattern_index != start {
+                step_selection(&mut app, back);
+                assert_eq!(app.selected_pattern_index, start, "seed {seed}, {step:?}");
+            }
+        }
+    }
+}
@@ -0,0 +1,280 @@
+use std::time::{Duration, Instant};
+
+use crate::{
+    App, Pattern, PatternParent,
+    game::{
+        Action, Event, Game, GameError, Phase, Player, Seat, SeatId, SeatKind,
+        guesser::{BotGuesser, Strategy},
+        master::BotMaster,
+    },
+    helpers::{
+        gallery::{select_last_pattern, select_pattern},
+        rng::Rng,
+    },
+};
+
+fn seat(name: &str, kind: SeatKind) -> Seat {
+    Seat {
+        name: name.to_string(),
+        kind,
+        tokens: 0,
+    }
+}
+
+/// Starts a new game. The computer is the master when `bot_master` is set,
+/// otherwise the user is. Every strategy adds a bot guesser.
+pub fn start_game(app: &mut App, bot_master: bool, strategies: &[Strategy]) {
+    let mut seats = vec![];
+    app.players = vec![];
+
+    if bot_master {
+        let master = BotMaster::new(Rng::new(app.rng.next_u64()));
+        app.players.push((seats.len(), Box::new(master)));
+        seats.push(seat("Computer", SeatKind::Bot));
+    }
+    app.human_seat = seats.len();
+    seats.push(seat(&app.player_name, SeatKind::Human));
+
+    for strategy in strategies {
+        let guesser = BotGuesser::new(*strategy, Rng::new(app.rng.next_u64()));
+        app.players
+            .push((seats.len(), Box::new(guesser) as Box<dyn Player>));
+        seats.push(seat(&format!("{strategy} bot"), SeatKind::Bot));
+    }
+
+    let master = if bot_master { 0 } else { app.human_seat };
+    app.game = Some(Game::new(seats, master));
+    app.patterns.clear();
+    app.deleted.clear();
+    app.forget_indexes();
+    app.selected_pattern_index = 0;
+    app.render_start_index = 0;
+    app.render_end_index = 0;
+    app.notifications.info(if bot_master {
+        "New game, the computer picked a rule"
+    } else {
+        "New game, build a valid and an invalid koan for your rule, R rates it"
+    });
+
+    run_bots(app);
+}
+
+/// A game as the lobby sets it up.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GameSetup {
+    pub bot_master: bool,
+    /// Whether a bot plays, one flag per `Strategy::ALL`.
+    pub bots: [bool; Strategy::ALL.len()],
+    pub think_delay: Duration,
+}
+
+impl GameSetup {
+    pub fn strategies(&self) -> Vec<Strategy> {
+        Strategy::ALL
+            .iter()
+            .zip(self.bots)
+            .filter(|(_, plays)| *plays)
+            .map(|(strategy, _)| *strategy)
+            .collect()
+    }
+}
+
+/// Starts the game set up in the lobby.
+pub fn start_setup(app: &mut App, setup: GameSetup) {
+    app.think_delay = setup.think_delay;
+    start_game(app, setup.bot_master, &setup.strategies());
+}
+
+/// Starts a new game with the computer as master and the user as the guesser.
+pub fn start_bot_master_game(app: &mut App) {
+    start_game(app, true, &[]);
+}
+
+/// What `Enter` does in a game: the editor pattern becomes an opening koan,
+/// a koan to judge, a verdict or a counterexample, depending on the phase.
+pub fn enter_action(app: &App) -> Option<Action> {
+    let game = app.game.as_ref()?;
+    let koan = app.data_big.clone();
+    match &game.phase {
+        Phase::Opening => Some(Action::Open(koan)),
+        Phase::Turn(_) => Some(Action::Submit {
+            pattern: koan.data,
+            prediction: Some(koan.valid),
+        }),
+        Phase::Judging { .. } => Some(Action::Judge(koan.valid)),
+        Phase::Refuting { .. } => Some(Action::Counterexample(koan)),
+        Phase::Over { .. } => None,
+    }
+}
+
+/// Plays an action for the user's seat, then lets the bots respond.
+pub fn play(app: &mut App, action: Action) -> Result<(), GameError> {
+    let events = apply(app, action)?;
+    handle_events(app, events);
+    run_bots(app);
+    Ok(())
+}
+
+/// Plays the editor koan, see `enter_action`. The editor is cleared once the
+/// koan is played, before the bots respond, so that a koan a bot hands the
+/// master to judge stays in it.
+pub fn play_editor_koan(app: &mut App) -> Result<(), GameError> {
+    let action = enter_action(app).ok_or(GameError::GameOver)?;
+    let events = apply(app, action)?;
+    app.data_big = PatternParent::new(Pattern::default(), app.data_big.valid);
+    handle_events(app, events);
+    run_bots(app);
+    Ok(())
+}
+
+fn apply(app: &mut App, action: Action) -> Result<Vec<Event>, GameError> {
+    let Some(game) = app.game.as_mut() else {
+        return Err(GameError::WrongAction);
+    };
+    game.apply(app.human_seat, action)
+}
+
+/// Lets bots act until the game waits for a human or is over. With a think
+/// delay at most one bot moves per delay, so call this again on every tick.
+pub fn run_bots(app: &mut App) {
+    loop {
+        if app.last_bot_move.elapsed() < app.think_delay {
+            return;
+        }
+        let Some(game) = app.game.as_mut() else {
+            return;
+        };
+        let Some(seat) = game.to_act() else {
+            return;
+        };
+        if game.seats[seat].kind == SeatKind::Human {
+            return;
+        }
+        let Some((_, player)) = app.players.iter_mut().find(|(s, _)| *s == seat) else {
+            return;
+        };
+        let Some(action) = player.act(game, seat) else {
+            return;
+        };
+
+        app.last_bot_move = Instant::now();
+        match game.apply(seat, action) {
+            Ok(events) => handle_events(app, events),
+            Err(err) => {
+                let message = format!("{}: {err}", game.seats[seat].name);
+                app.notifications.error(message);
+                return;
+            }
+        }
+    }
+}
+
+fn handle_events(app: &mut App, events: Vec<Event>) {
+    let Some(game) = app.game.as_ref() else {
+        return;
+    };
+
+    let name = |seat: SeatId| game.seats[seat].name.clone();
+    let human_is_master = game.master == app.human_seat;
+    let mut messages = vec![];
+    let mut new_koans = vec![];
+    let mut to_judge = None;
+
+    for event in &events {
+        for (_, player) in app.players.iter_mut() {
+            player.observe(game, event);
+        }
+
+        match event {
+            Event::Koan { seat, index } => {
+                let verdict = if game.koans[*index].valid {
+                    "valid"
+                } else {
+                    "invalid"
+                };
+                messages.push(format!(
+                    "{} submitted koan #{index}, it is {verdict}",
+                    name(*seat)
+                ));
+                new_koans.push(game.koans[*index].clone());
+            }
+            Event::Submitted { seat } => {
+                messages.push(format!("{} built a koan", name(*seat)));
+                if let Phase::Judging { pattern, .. } = &game.phase
+                    && human_is_master
+                {
+                    messages.push("i: toggle verdict, Enter: judge".to_string());
+                    to_judge = Some(*pattern);
+                }
+            }
+            Event::TokenWon { seat } => messages.push(format!("{} won a token", name(*seat))),
+            Event::Guessed { seat, rule } => {
+                messages.push(format!("{} guessed \"{rule}\"", name(*seat)));
+                if human_is_master {
+                    messages.push("Y: accept, or build a counterexample".to_string());
+                }
+            }
+            Event::Refuted { seat, index } => {
+                messages.push(format!(
+                    "{} guessed wrong, #{index} is a counterexample",
+                    name(*seat)
+                ));
+                new_koans.push(game.koans[*index].clone());
+            }
+            Event::Won { seat, rule } => {
+                messages.push(format!("{} found the rule: {rule}", name(*seat)))
+            }
+        }
+    }
+
+    for message in messages {
+        app.notifications.info(message);
+    }
+    for koan in new_koans {
+        app.patterns.push(koan);
+        select_last_pattern(app);
+    }
+    if let Some(pattern) = to_judge {
+        app.data_big = PatternParent::new(pattern, true);
+        app.open_editor();
+        // Judging it is the master allowing it, so it is only pointed out.
+        if let Some(duplicate) = app.repeated_koan() {
+            app.notifications.warn(format!(
+                "The koan to judge is {} #{}",
+                duplicate.how(),
+                duplicate.index
+            ));
+            app.duplicate = Some(duplicate);
+            select_pattern(app, duplicate.index);
+        }
+    } else if human_is_master {
+        app.data_big = PatternParent::new(Pattern::default(), app.data_big.valid);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::notation::parse_parent;
+    use std::time::Duration;
+
+    #[test]
+    fn keeps_the_koan_to_judge_in_the_editor() {
+        let mut app = App::new();
+        app.rng = Rng::new(1);
+        app.think_delay = Duration::ZERO;
+        start_game(&mut app, false, &[Strategy::Random]);
+
+        for notation in ["+ Lr^", "- Sb/"] {
+            app.data_big = parse_parent(notation).unwrap();
+            play_editor_koan(&mut app).unwrap();
+        }
+
+        let Some(Phase::Judging { pattern, .. }) = app.game.as_ref().map(|game| &game.phase) else {
+            panic!("the bot did not build a koan");
+        };
+        assert!(!pattern.is_empty());
+        assert_eq!(app.data_big.data, *pattern);
+        assert!(app.editing());
+    }
+}
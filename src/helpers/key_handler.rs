@@ -1,345 +1,1439 @@
-use crossterm::event::{KeyCode, KeyEvent};
+use std::time::Instant;
+use std::{fmt, io};
 
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+
+use crate::formats::game_file::{gallery_to_game_file, parse_tags};
+use crate::formats::notation::{parent_to_notation, parse_parent};
+use crate::formats::svg::{gallery_to_svg, pattern_to_svg};
+use crate::formats::text::{TextStyle, gallery_to_text, pattern_to_text};
+use crate::game::guesser::Strategy;
+use crate::game::{Action, GameError, Phase};
+use crate::helpers::clip::{Clip, in_rectangle};
+use crate::helpers::clipboard::copy_to_clipboard;
+use crate::helpers::command::{Command, complete_command, parse_command};
+use crate::helpers::gallery::{
+    Filter, MAX_GALLERY_COLUMNS, MAX_GALLERY_ROWS, Step, page_selection, scroll_rows,
+    scroll_to_selection, select_last_pattern, select_pattern, set_filter, step_selection,
+};
+use crate::helpers::game_handler::{
+    play, play_editor_koan, start_bot_master_game, start_game, start_setup,
+};
+use crate::helpers::transform::Transform;
+use crate::helpers::vim::Vim;
+use crate::solver::advisor::suggest;
+use crate::solver::difficulty::estimate;
+use crate::solver::rule::Rule;
+use crate::solver::space::encode;
+use crate::ui::palette::{Attribute, PALETTE, PaletteEntry, palette_entry_at};
+use crate::ui::text_input::{InputEvent, TextInput};
 use crate::{
-    App, COLOR_BLUE, COLOR_RED, COLOR_YELLOW, Datos, HEIGHT, Mode, Pattern, PyramidType,
-    SymbolSize, WIDTH,
+    AnnotationDraft, App, COLOR_BLUE, COLOR_RED, COLOR_YELLOW, Carried, Confirm, ConfirmAction,
+    Edit, GlyphSet, HEIGHT, Keymap, Lobby, Pattern, PatternParent, Piece, PieceStyle, Prompt,
+    PromptKind, PyramidType, REPLAY_STEP, Replay, Screen, SymbolSize, WIDTH,
 };
 
+/// Why a key did nothing. Shown in the footer for a moment instead of
+/// crashing the terminal session.
+#[derive(Debug)]
+pub enum ActionError {
+    /// The cell under the cursor has no piece to change.
+    EmptyCell,
+    /// The action needs a saved pattern, but the gallery is empty.
+    EmptyGallery,
+    Game(GameError),
+    Write {
+        path: String,
+        source: io::Error,
+    },
+    Clipboard(io::Error),
+    /// The gallery holds the koans of the running game.
+    GameRunning,
+    /// Only the master may do this during a game.
+    NotMaster,
+    NothingToUndo,
+    NoEditToUndo,
+    NothingToRedo,
+    /// Paste before anything was copied.
+    EmptyClip,
+    NoSuchKoan(usize),
+    /// Text that was typed does not make sense.
+    Invalid(String),
+}
+
+impl fmt::Display for ActionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActionError::EmptyCell => f.write_str("there is no piece on this cell"),
+            ActionError::EmptyGallery => f.write_str("there are no saved patterns yet"),
+            ActionError::Game(err) => write!(f, "{err}"),
+            ActionError::Write { path, source } => write!(f, "could not write {path}: {source}"),
+            ActionError::Clipboard(err) => write!(f, "could not copy: {err}"),
+            ActionError::GameRunning => {
+                f.write_str("the koans of a running game can not be changed")
+            }
+            ActionError::NotMaster => f.write_str("only the master can do that during a game"),
+            ActionError::NothingToUndo => f.write_str("there is no deleted koan to bring back"),
+            ActionError::NoEditToUndo => f.write_str("there is no piece change to undo"),
+            ActionError::NothingToRedo => f.write_str("there is no undone piece change to redo"),
+            ActionError::EmptyClip => f.write_str("copy a piece, a block or a koan first"),
+            ActionError::NoSuchKoan(index) => write!(f, "there is no koan #{index}"),
+            ActionError::Invalid(reason) => f.write_str(reason),
+        }
+    }
+}
+
+impl std::error::Error for ActionError {}
+
+impl From<GameError> for ActionError {
+    fn from(err: GameError) -> Self {
+        ActionError::Game(err)
+    }
+}
+
+/// Whether to hold back the editor koan because it repeats a saved one, see
+/// `App::repeated_koan`. The first time it warns and selects the earlier
+/// koan, pressing Enter again to `verb` it lets it through.
+fn hold_repeat(app: &mut App, verb: &str) -> bool {
+    let code = encode(&app.data_big.data);
+    let allowed = app
+        .duplicate
+        .is_some_and(|duplicate| duplicate.code == code);
+    let Some(duplicate) = app.repeated_koan().filter(|_| !allowed) else {
+        app.duplicate = None;
+        return false;
+    };
+    let index = duplicate.index;
+    let verdict = match app.patterns[index].valid == app.data_big.valid {
+        true => "",
+        false => " with the opposite verdict",
+    };
+    app.notifications.warn(format!(
+        "This koan is {} #{index}{verdict}, press Enter again to {verb} it anyway.",
+        duplicate.how()
+    ));
+    app.duplicate = Some(duplicate);
+    select_pattern(app, index);
+    true
+}
+
+/// Saves the editor koan to the gallery and selects it, unless it is held
+/// back as a repeat, see `hold_repeat`.
 fn save_pattern(app: &mut App) {
+    if hold_repeat(app, "save") {
+        return;
+    }
+
     app.patterns.push(app.data_big.clone());
     // app.patterns.insert(0, app.data_big.data.clone());
-    app.data_big.data = Pattern(vec![]);
+    app.data_big = PatternParent::new(Pattern::default(), app.data_big.valid);
+    select_last_pattern(app);
+    app.notifications
+        .info(format!("Saved koan #{}", app.patterns.len() - 1));
 }
 
+/// Handles a key press, showing what went wrong in the footer.
 pub fn on_key_event(app: &mut App, key: KeyEvent) {
+    let pos = app.current_pos;
+    let vim_keys =
+        app.keymap == Keymap::Vim && matches!(app.screen(), Screen::Gallery | Screen::Editor);
+    let result = match vim_keys {
+        true => on_vim_key_event(app, key),
+        false => handle_key(app, key),
+    };
+    let result = result.and_then(|()| match app.stamp && app.current_pos != pos {
+        true => stamp(app),
+        false => Ok(()),
+    });
+    if let Err(err) = result {
+        app.notifications.error(err.to_string());
+    }
+    // A key can change a koan so the filter hides it, e.g. `i`.
+    if app.filter != Filter::All {
+        scroll_to_selection(app);
+    }
+}
+
+/// Sets the palette with a click on it.
+pub fn on_mouse_event(app: &mut App, mouse: MouseEvent) {
+    if mouse.kind != MouseEventKind::Down(MouseButton::Left)
+        || !matches!(app.screen(), Screen::Editor)
+    {
+        return;
+    }
+    let result = match palette_entry_at(app, mouse.column, mouse.row) {
+        Some(PaletteEntry::Attribute(attribute)) => set_attribute(app, attribute),
+        Some(PaletteEntry::Stamp) => toggle_stamp(app),
+        None => Ok(()),
+    };
+    if let Err(err) = result {
+        app.notifications.error(err.to_string());
+    }
+}
+
+/// Runs what a key means in the vim preset, the default keys otherwise.
+fn on_vim_key_event(app: &mut App, key: KeyEvent) -> Result<(), ActionError> {
+    match app.vim.translate(key, app.selection.is_some()) {
+        Some(commands) => commands
+            .into_iter()
+            .try_for_each(|command| run_command(app, command)),
+        None => handle_key(app, key),
+    }
+}
+
+/// The default keys.
+fn handle_key(app: &mut App, key: KeyEvent) -> Result<(), ActionError> {
+    match app.screen() {
+        Screen::Gallery => on_gallery_key_event(app, key),
+        Screen::Editor => on_editor_key_event(app, key),
+        Screen::Help => {
+            if matches!(key.code, KeyCode::Esc | KeyCode::Char('q' | 'h')) {
+                app.pop_screen();
+            }
+            Ok(())
+        }
+        Screen::Solver => on_solver_key_event(app, key),
+        Screen::Advisor => {
+            on_advisor_key_event(app, key);
+            Ok(())
+        }
+        Screen::Log => {
+            on_log_key_event(app, key);
+            Ok(())
+        }
+        Screen::Annotation(_) => {
+            on_annotation_key_event(app, key);
+            Ok(())
+        }
+        Screen::Prompt(_) => on_prompt_key_event(app, key),
+        Screen::Confirm(_) => on_confirm_key_event(app, key),
+        Screen::Lobby(_) => on_lobby_key_event(app, key),
+        Screen::Replay(_) => {
+            on_replay_key_event(app, key);
+            Ok(())
+        }
+    }
+}
+
+/// Keys for the koan in the editor. Everything else works as in the gallery.
+fn on_editor_key_event(app: &mut App, key: KeyEvent) -> Result<(), ActionError> {
+    // Ctrl keys, like Ctrl-y for redo, are the same everywhere.
+    if key.modifiers.contains(KeyModifiers::CONTROL) {
+        return on_gallery_key_event(app, key);
+    }
+    match key.code {
+        KeyCode::Esc if app.selection.is_some() || app.carried.is_some() || app.stamp => {
+            app.selection = None;
+            app.carried = None;
+            app.stamp = false;
+        }
+        KeyCode::Esc | KeyCode::Char('v') => app.close_editor(),
+        KeyCode::Backspace | KeyCode::Delete => clear_piece(app)?,
+        KeyCode::Char('i') => app.data_big.valid = !app.data_big.valid,
+        KeyCode::Char('t') => {
+            let draft = AnnotationDraft::new(None, &app.data_big.annotation);
+            app.push_screen(Screen::Annotation(Box::new(draft)));
+        }
+        KeyCode::Char('y') => copy_notation(app, &parent_to_notation(&app.data_big))?,
+        _ => return on_gallery_key_event(app, key),
+    }
+    Ok(())
+}
+
+fn on_gallery_key_event(app: &mut App, key: KeyEvent) -> Result<(), ActionError> {
     match (key.modifiers, key.code) {
+        (KeyModifiers::CONTROL, KeyCode::Char('s')) => {
+            let path = app.save_path.clone();
+            let game = gallery_to_game_file(&app.patterns);
+            write_export(app, &path, &game)?;
+            app.saved_game = game;
+        }
+        (_, KeyCode::Esc) if app.selection.is_some() || app.carried.is_some() || app.stamp => {
+            app.selection = None;
+            app.carried = None;
+            app.stamp = false;
+        }
+        (_, KeyCode::Esc | KeyCode::Char('q')) if app.has_unsaved_changes() => confirm(
+            app,
+            ConfirmAction::Quit,
+            "Quit without saving? Ctrl-s saves the koans.",
+        )?,
         (_, KeyCode::Esc | KeyCode::Char('q')) => app.quit(),
 
+        (KeyModifiers::CONTROL, KeyCode::Char('z')) => restore_edit(app, false)?,
+        (KeyModifiers::CONTROL, KeyCode::Char('y')) => restore_edit(app, true)?,
+        (KeyModifiers::CONTROL, KeyCode::Right) => transform_pattern(app, Transform::Shift(1, 0))?,
+        (KeyModifiers::CONTROL, KeyCode::Left) => transform_pattern(app, Transform::Shift(-1, 0))?,
+        (KeyModifiers::CONTROL, KeyCode::Down) => transform_pattern(app, Transform::Shift(0, 1))?,
+        (KeyModifiers::CONTROL, KeyCode::Up) => transform_pattern(app, Transform::Shift(0, -1))?,
+
         // Select Directions
         (_, KeyCode::Right) => {
             if app.current_pos.0 > WIDTH - 2 {
                 app.current_pos.0 = 0
             } else {
-                app.current_pos.0 = app.current_pos.0 + 1
+                app.current_pos.0 += 1
             }
         }
         (_, KeyCode::Left) => {
             if app.current_pos.0 == 0 {
                 app.current_pos.0 = WIDTH - 1
             } else {
-                app.current_pos.0 = app.current_pos.0 - 1
+                app.current_pos.0 -= 1
             }
         }
         (_, KeyCode::Down) => {
             if app.current_pos.1 > HEIGHT - 2 {
                 app.current_pos.1 = 0
             } else {
-                app.current_pos.1 = app.current_pos.1 + 1
+                app.current_pos.1 += 1
             }
         }
         (_, KeyCode::Up) => {
             if app.current_pos.1 == 0 {
                 app.current_pos.1 = HEIGHT - 1
             } else {
-                app.current_pos.1 = app.current_pos.1 - 1
+                app.current_pos.1 -= 1
             }
         }
 
         // Symbol Manipulation
         (_, KeyCode::Char('r')) => {
-            let pattern = if app.mode == Mode::EDITING {
-                app.active_pattern()
-            } else {
-                app.active_viewed_pattern()
-            };
-            let object = pattern
-                .iter()
-                .find(|d| d.pos.0 == app.current_pos.0 && d.pos.1 == app.current_pos.1);
-
-            let mut new_type = PyramidType::Straight;
-            let mut old_color = COLOR_BLUE;
-            let mut old_size = SymbolSize::MEDIUM;
-            if object.is_some() {
-                old_color = object.unwrap().color;
-                old_size = object.unwrap().size;
-                let index_of_selected = pattern
-                    .iter()
-                    .position(|d| d.pos.0 == app.current_pos.0 && d.pos.1 == app.current_pos.1);
-
-                new_type = match object.unwrap().pyramid_type {
-                    PyramidType::Straight => PyramidType::Angled,
-                    _ => PyramidType::Straight,
-                };
-
-                if app.mode == Mode::EDITING {
-                    app.data_big.data.remove(index_of_selected.unwrap());
-                } else {
-                    app.patterns
-                        .get_mut(app.selected_pattern_index)
-                        .unwrap()
-                        .data
-                        .remove(index_of_selected.unwrap());
-                }
-            }
-
-            if app.mode == Mode::EDITING {
-                app.data_big.data.push(Datos {
-                    pyramid_type: new_type,
-                    pos: app.current_pos,
-                    color: old_color,
-                    size: old_size,
+            let pos = app.current_pos;
+            let brush = app.brush;
+            app.edit_target(|pattern| {
+                let turned = pattern.update(pos, |piece| {
+                    piece.pyramid_type = match piece.pyramid_type {
+                        PyramidType::Straight => PyramidType::Angled,
+                        PyramidType::Angled => PyramidType::Straight,
+                    };
+                    piece.turned = false;
                 });
-            } else {
-                app.patterns
-                    .get_mut(app.selected_pattern_index)
-                    .unwrap()
-                    .data
-                    .push(Datos {
-                        pyramid_type: new_type,
-                        pos: app.current_pos,
-                        color: old_color,
-                        size: old_size,
-                    });
-            }
+                if turned.is_none() {
+                    pattern.set(pos, brush);
+                }
+            })?;
         }
         (_, KeyCode::Char('c')) => {
-            let pattern = if app.mode == Mode::EDITING {
-                app.active_pattern()
-            } else {
-                app.active_viewed_pattern()
-            };
-            let object = pattern
-                .iter()
-                .find(|d| d.pos.0 == app.current_pos.0 && d.pos.1 == app.current_pos.1);
-
-            let index_of_selected = pattern
-                .iter()
-                .position(|d| d.pos.0 == app.current_pos.0 && d.pos.1 == app.current_pos.1);
-
-            let new_color = match object.unwrap().color {
-                COLOR_RED => COLOR_BLUE,
-                COLOR_BLUE => COLOR_YELLOW,
-                COLOR_YELLOW => COLOR_RED,
-                _ => COLOR_RED,
-            };
-            let old_type = object.unwrap().pyramid_type;
-            let old_size = object.unwrap().size;
-
-            if app.mode == Mode::EDITING {
-                app.data_big.data.remove(index_of_selected.unwrap());
-
-                app.data_big.data.push(Datos {
-                    pyramid_type: old_type,
-                    pos: app.current_pos,
-                    color: new_color,
-                    size: old_size,
-                });
-            } else {
-                app.patterns
-                    .get_mut(app.selected_pattern_index)
-                    .unwrap()
-                    .data
-                    .remove(index_of_selected.unwrap());
-
-                app.patterns
-                    .get_mut(app.selected_pattern_index)
-                    .unwrap()
-                    .data
-                    .push(Datos {
-                        pyramid_type: old_type,
-                        pos: app.current_pos,
-                        color: new_color,
-                        size: old_size,
-                    });
-            }
+            let pos = app.current_pos;
+            app.edit_target(|pattern| {
+                pattern.update(pos, |piece| {
+                    piece.color = match piece.color {
+                        COLOR_RED => COLOR_BLUE,
+                        COLOR_BLUE => COLOR_YELLOW,
+                        COLOR_YELLOW => COLOR_RED,
+                        _ => COLOR_RED,
+                    }
+                })
+            })?
+            .ok_or(ActionError::EmptyCell)?;
         }
         (_, KeyCode::Char('s')) => {
-            let pattern = if app.mode == Mode::EDITING {
-                app.active_pattern()
-            } else {
-                app.active_viewed_pattern()
-            };
+            let pos = app.current_pos;
+            app.edit_target(|pattern| {
+                pattern.update(pos, |piece| {
+                    piece.size = match piece.size {
+                        SymbolSize::SMALL => SymbolSize::MEDIUM,
+                        SymbolSize::MEDIUM => SymbolSize::LARGE,
+                        SymbolSize::LARGE => SymbolSize::SMALL,
+                    }
+                })
+            })?
+            .ok_or(ActionError::EmptyCell)?;
+        }
 
-            let object = pattern
-                .iter()
-                .find(|d| d.pos.0 == app.current_pos.0 && d.pos.1 == app.current_pos.1);
+        (_, KeyCode::Enter) if app.game.is_some() => play_koan(app)?,
+        (_, KeyCode::Char('Y')) if app.game.is_some() => play(app, Action::Accept)?,
+        (_, KeyCode::Enter) => save_pattern(app),
 
-            if object.is_none() {
-                return;
+        (_, KeyCode::Char('a')) => scroll_rows(app, -1),
+        (_, KeyCode::Char('d')) => scroll_rows(app, 1),
+        (_, KeyCode::PageUp) => page_selection(app, -1),
+        (_, KeyCode::PageDown) => page_selection(app, 1),
+        (_, KeyCode::Home) => select_pattern(app, 0),
+        (_, KeyCode::End) => select_last_pattern(app),
+        (_, KeyCode::Char(':')) => {
+            let input = TextInput::default()
+                .with_history(app.command_history.clone())
+                .with_validator(|text| parse_command(text).map(|_| ()))
+                .with_completer(complete_command);
+            app.push_screen(Screen::Prompt(Prompt {
+                kind: PromptKind::Command,
+                input,
+            }));
+        }
+        (_, KeyCode::Char('j')) => {
+            let input = TextInput::default().with_validator(validate_index);
+            app.push_screen(Screen::Prompt(Prompt {
+                kind: PromptKind::Jump,
+                input,
+            }));
+        }
+
+        (_, KeyCode::Char('m')) => {
+            rearrangeable(app)?;
+            if app.pattern_index >= app.patterns.len() {
+                return Err(ActionError::EmptyGallery);
             }
-            let index_of_selected = pattern
-                .iter()
-                .position(|d| d.pos.0 == app.current_pos.0 && d.pos.1 == app.current_pos.1);
-
-            let new_size = match object.unwrap().size {
-                SymbolSize::SMALL => SymbolSize::MEDIUM,
-                SymbolSize::MEDIUM => SymbolSize::LARGE,
-                SymbolSize::LARGE => SymbolSize::SMALL,
-            };
-            let old_type = object.unwrap().pyramid_type;
-            let old_color = object.unwrap().color;
+            let question = format!("Replace koan #{} with the editor koan?", app.pattern_index);
+            confirm(app, ConfirmAction::Overwrite, &question)?;
+        }
 
-            if app.mode == Mode::EDITING {
-                app.data_big.data.remove(index_of_selected.unwrap());
+        (_, KeyCode::Delete) => {
+            let index = app.selected_pattern_index;
+            rearrangeable(app)?;
+            app.selected_pattern()?;
+            confirm(
+                app,
+                ConfirmAction::Delete,
+                &format!("Delete koan #{index}?"),
+            )?;
+        }
+        (_, KeyCode::Char('u')) => undo_delete(app)?,
+        (_, KeyCode::Char('[')) => move_pattern(app, -1)?,
+        (_, KeyCode::Char(']')) => move_pattern(app, 1)?,
+        (_, KeyCode::Char('C')) => {
+            rearrangeable(app)?;
+            let index = app.selected_pattern_index;
+            let copy = app.selected_pattern()?.clone();
+            app.patterns.insert(index + 1, copy);
+            app.forget_indexes();
+            select_pattern(app, index + 1);
+            app.notifications
+                .info(format!("Copied koan #{index} to #{}", index + 1));
+        }
+        (_, KeyCode::Char('l')) => {
+            let pattern = app.selected_pattern()?;
+            app.data_big = PatternParent::new(pattern.data, pattern.valid);
+            app.open_editor();
+        }
+        (_, KeyCode::Char('e')) => app.open_editor(),
+        (_, KeyCode::Char('h')) => app.push_screen(Screen::Help),
+        (_, KeyCode::Char('>')) => {
+            app.pattern_rows = (app.pattern_rows - 1).max(1);
+            scroll_to_selection(app);
+        }
+        (_, KeyCode::Char('<')) => {
+            app.patterns_per_row = (app.patterns_per_row - 1).max(1);
+            scroll_to_selection(app);
+        }
+        (_, KeyCode::Char('.')) => {
+            app.pattern_rows = (app.pattern_rows + 1).min(MAX_GALLERY_ROWS);
+            scroll_to_selection(app);
+        }
+        (_, KeyCode::Char(',')) => {
+            app.patterns_per_row = (app.patterns_per_row + 1).min(MAX_GALLERY_COLUMNS);
+            scroll_to_selection(app);
+        }
 
-                app.data_big.data.push(Datos {
-                    pyramid_type: old_type,
-                    pos: app.current_pos,
-                    color: old_color,
-                    size: new_size,
+        (_, KeyCode::Char('t')) => {
+            let index = app.selected_pattern_index;
+            let draft = AnnotationDraft::new(Some(index), &app.selected_pattern()?.annotation);
+            app.push_screen(Screen::Annotation(Box::new(draft)));
+        }
+        (_, KeyCode::Char('R')) => {
+            let input = TextInput::default()
+                .with_history(app.rule_history.clone())
+                .with_validator(|text| {
+                    text.parse::<Rule>()
+                        .map(|_| ())
+                        .map_err(|err| err.to_string())
                 });
-            } else {
-                app.patterns
-                    .get_mut(app.selected_pattern_index)
-                    .unwrap()
-                    .data
-                    .remove(index_of_selected.unwrap());
-
-                app.patterns
-                    .get_mut(app.selected_pattern_index)
-                    .unwrap()
-                    .data
-                    .push(Datos {
-                        pyramid_type: old_type,
-                        pos: app.current_pos,
-                        color: old_color,
-                        size: new_size,
-                    });
-            };
+            app.push_screen(Screen::Prompt(Prompt {
+                kind: PromptKind::Rule,
+                input,
+            }));
+        }
+        (_, KeyCode::Char('N')) => {
+            let input = TextInput::new(&app.player_name).with_validator(validate_name);
+            app.push_screen(Screen::Prompt(Prompt {
+                kind: PromptKind::Name,
+                input,
+            }));
+        }
+        (_, KeyCode::Char('L')) => {
+            app.push_screen(Screen::Log);
+            app.log_scroll = 0;
+        }
+        (_, KeyCode::Char('?')) if app.assistant_enabled && app.game_running() => confirm(
+            app,
+            ConfirmAction::RevealRules,
+            "Show the rules that are still possible?",
+        )?,
+        (_, KeyCode::Char('?')) => run_confirmed(app, ConfirmAction::RevealRules)?,
+        (_, KeyCode::Char('M')) => new_game(app, ConfirmAction::BotMasterGame)?,
+        (_, KeyCode::Char('B')) => new_game(app, ConfirmAction::BotGame)?,
+        (_, KeyCode::Char('J')) => app.push_screen(Screen::Lobby(Lobby::new(app.think_delay))),
+        (_, KeyCode::Char('F')) => {
+            if app.patterns.is_empty() {
+                return Err(ActionError::EmptyGallery);
+            }
+            app.push_screen(Screen::Replay(Replay {
+                index: 0,
+                playing: None,
+            }));
+            select_pattern(app, 0);
+        }
+        (_, KeyCode::Char('n')) => {
+            app.push_screen(Screen::Advisor);
+            new_suggestions(app);
         }
-        (_, KeyCode::Backspace) => {
-            let object = app
-                .active_pattern()
-                .iter()
-                .find(|d| d.pos.0 == app.current_pos.0 && d.pos.1 == app.current_pos.1);
 
-            if object.is_none() {
-                return;
+        (_, KeyCode::Char('g')) => {
+            app.piece_style = match app.piece_style {
+                PieceStyle::Full => PieceStyle::Compact,
+                PieceStyle::Compact => PieceStyle::Full,
             }
-            let index_of_selected = app
-                .active_pattern()
-                .iter()
-                .position(|d| d.pos.0 == app.current_pos.0 && d.pos.1 == app.current_pos.1);
+        }
+        (_, KeyCode::Char('G')) => {
+            app.glyphs = match app.glyphs {
+                GlyphSet::Unicode => GlyphSet::Ascii,
+                GlyphSet::Ascii => GlyphSet::Unicode,
+            }
+        }
 
-            app.data_big.data.remove(index_of_selected.unwrap());
+        (_, KeyCode::Char('x')) => {
+            let index = app.selected_pattern_index;
+            let pattern = app.selected_pattern()?;
+            let svg = pattern_to_svg(pattern, index);
+            write_export(app, &format!("zendui-pattern-{index}.svg"), &svg)?;
+        }
+        (_, KeyCode::Char('X')) => {
+            let svg = gallery_to_svg(&app.patterns, app.patterns_per_row);
+            write_export(app, "zendui-gallery.svg", &svg)?;
         }
 
-        (_, KeyCode::Enter) => {
-            save_pattern(app);
-            app.pattern_index = app.patterns.len() - 1;
-            app.selected_pattern_index = app.patterns.len() - 1;
-            app.render_end_index = app.pattern_index;
-            step_right(app, 1);
+        (_, KeyCode::Char('o')) => {
+            let index = app.selected_pattern_index;
+            let pattern = app.selected_pattern()?;
+            let path = format!("zendui-pattern-{index}");
+            let plain = pattern_to_text(pattern, index, TextStyle::Plain);
+            let ansi = pattern_to_text(pattern, index, TextStyle::Ansi);
+            write_export(app, &format!("{path}.txt"), &plain)?;
+            write_export(app, &format!("{path}.ans"), &ansi)?;
+        }
+        (_, KeyCode::Char('O')) => {
+            let plain = gallery_to_text(&app.patterns, app.patterns_per_row, TextStyle::Plain);
+            let ansi = gallery_to_text(&app.patterns, app.patterns_per_row, TextStyle::Ansi);
+            write_export(app, "zendui-gallery.txt", &plain)?;
+            write_export(app, "zendui-gallery.ans", &ansi)?;
         }
 
-        (_, KeyCode::Char('a')) => {
-            step_left(app, 1);
+        (_, KeyCode::Char('y')) => {
+            let notation = parent_to_notation(app.selected_pattern()?);
+            copy_notation(app, &notation)?;
         }
 
-        (_, KeyCode::Char('d')) => {
-            step_right(app, 1);
+        (_, KeyCode::Char('i')) => {
+            rearrangeable(app)?;
+            let selected = app
+                .patterns
+                .get_mut(app.selected_pattern_index)
+                .ok_or(ActionError::EmptyGallery)?;
+            selected.valid = !selected.valid;
         }
+        (_, KeyCode::Char(key @ '1'..='8')) => {
+            if let Some(attribute) = palette_key(key) {
+                set_attribute(app, attribute)?;
+            }
+        }
+        (_, KeyCode::Char('f')) => toggle_stamp(app)?,
+        (_, KeyCode::Char('w')) => transform_pattern(app, Transform::Rotate(1))?,
+        (_, KeyCode::Char('H')) => transform_pattern(app, Transform::MirrorHorizontal)?,
+        (_, KeyCode::Char('V')) => transform_pattern(app, Transform::MirrorVertical)?,
+        (_, KeyCode::Char('P')) => transform_pattern(app, Transform::CycleColors)?,
+        (_, KeyCode::Char('b')) => toggle_selection(app),
+        (_, KeyCode::Char('k')) => yank(app)?,
+        (_, KeyCode::Char('K')) => yank_koan(app)?,
+        (_, KeyCode::Char('p')) => put(app)?,
+        (_, KeyCode::Char('z')) => carry(app)?,
+        (_, KeyCode::Backspace) if !app.editing() => clear_piece(app)?,
+        (_, KeyCode::Char('W')) => step_selection(app, Step::Up),
+        (_, KeyCode::Char('A')) => step_selection(app, Step::Left),
+        (_, KeyCode::Char('S')) => step_selection(app, Step::Down),
+        (_, KeyCode::Char('D')) => step_selection(app, Step::Right),
+        _ => {}
+    }
+    Ok(())
+}
 
-        (_, KeyCode::Char('m')) => {
-            modify_pattern(app);
+/// Plays the editor koan in a game. A koan for the gallery is held back as a
+/// repeat like a saved one, see `hold_repeat`, a verdict is not.
+fn play_koan(app: &mut App) -> Result<(), ActionError> {
+    let judging = app
+        .game
+        .as_ref()
+        .is_some_and(|game| matches!(game.phase, Phase::Judging { .. }));
+    if !judging && hold_repeat(app, "play") {
+        return Ok(());
+    }
+    play_editor_koan(app)?;
+    Ok(())
+}
+
+/// Moves through the rule list, and guesses the selected rule in a game.
+fn on_solver_key_event(app: &mut App, key: KeyEvent) -> Result<(), ActionError> {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q' | '?') => {
+            app.pop_screen();
+        }
+        KeyCode::Up => app.solver_index = app.solver_index.saturating_sub(1),
+        KeyCode::Down => {
+            let last = app.solver_rules().len().saturating_sub(1);
+            app.solver_index = (app.solver_index + 1).min(last);
+        }
+        KeyCode::Char('d') => {
+            let Some(rule) = app
+                .solver_rules()
+                .into_iter()
+                .nth(app.solver_index)
+                .cloned()
+            else {
+                return Ok(());
+            };
+            let difficulty = estimate(&rule, &mut app.rng);
+            app.notifications
+                .info(format!("\"{rule}\" is {difficulty}"));
         }
+        KeyCode::Enter if app.game.is_some() => {
+            let Some(rule) = app
+                .solver_rules()
+                .into_iter()
+                .nth(app.solver_index)
+                .cloned()
+            else {
+                return Ok(());
+            };
+            app.pop_screen();
+            play(app, Action::Guess(rule))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
-        // modes
-        (_, KeyCode::Char('e')) => app.mode = crate::Mode::EDITING,
-        (_, KeyCode::Char('v')) => app.mode = crate::Mode::VIEWING,
-        (_, KeyCode::Char('>')) => {
-            app.pattern_rows = (app.pattern_rows - 1).max(1);
-            step_right(app, 1);
-            step_left(app, 1);
+fn on_advisor_key_event(app: &mut App, key: KeyEvent) {
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.pop_screen();
         }
-        (_, KeyCode::Char('<')) => {
-            app.patterns_per_row = (app.patterns_per_row - 1).max(1);
-            step_right(app, 1);
-            step_left(app, 1);
+        KeyCode::Char('n') => {
+            app.suggestion_index += 1;
+            if app.suggestion_index >= app.suggestions.len() {
+                new_suggestions(app);
+            }
         }
-        (_, KeyCode::Char('.')) => {
-            app.pattern_rows = app.pattern_rows + 1;
-            step_right(app, 1);
-            step_left(app, 1);
+        KeyCode::Enter => {
+            app.pop_screen();
+            if let Some(suggestion) = app.suggestions.get(app.suggestion_index) {
+                app.data_big.data = suggestion.pattern;
+                app.open_editor();
+            }
         }
-        (_, KeyCode::Char(',')) => {
-            app.patterns_per_row = app.patterns_per_row + 1;
-            step_right(app, 1);
-            step_left(app, 1);
+        _ => {}
+    }
+}
+
+/// Types into the annotation popup, one field at a time.
+fn on_annotation_key_event(app: &mut App, key: KeyEvent) {
+    let Some(Screen::Annotation(draft)) = app.screens.last_mut() else {
+        return;
+    };
+    let fields = draft.fields.len();
+    match draft.fields[draft.field].handle_key(key) {
+        InputEvent::Edited => {}
+        InputEvent::Cancelled => {
+            app.pop_screen();
+        }
+        InputEvent::Ignored => match key.code {
+            KeyCode::Tab | KeyCode::Down => draft.field = (draft.field + 1) % fields,
+            KeyCode::BackTab | KeyCode::Up => draft.field = (draft.field + fields - 1) % fields,
+            _ => {}
+        },
+        InputEvent::Submitted(_) => {
+            let Some(Screen::Annotation(draft)) = app.pop_screen() else {
+                return;
+            };
+            let [title, note, tags] = draft.fields;
+            let annotation = match draft.target {
+                Some(index) => app.patterns.get_mut(index).map(|p| &mut p.annotation),
+                None => Some(&mut app.data_big.annotation),
+            };
+            if let Some(annotation) = annotation {
+                annotation.title = title.text().trim().to_string();
+                annotation.note = note.text().trim().to_string();
+                annotation.tags = parse_tags(tags.text());
+            }
         }
+    }
+}
 
-        (_, KeyCode::Char('i')) => {
-            if app.mode == Mode::EDITING {
-                app.data_big.valid = !app.data_big.valid;
-            } else {
-                let asd = app.patterns.get_mut(app.selected_pattern_index).unwrap();
-                asd.valid = !asd.valid;
+/// Types into the open prompt and acts on the text once it is submitted.
+fn on_prompt_key_event(app: &mut App, key: KeyEvent) -> Result<(), ActionError> {
+    let Some(Screen::Prompt(prompt)) = app.screens.last_mut() else {
+        return Ok(());
+    };
+    let text = match prompt.input.handle_key(key) {
+        InputEvent::Submitted(text) => text,
+        InputEvent::Cancelled => {
+            app.pop_screen();
+            return Ok(());
+        }
+        InputEvent::Edited | InputEvent::Ignored => return Ok(()),
+    };
+    let Some(Screen::Prompt(prompt)) = app.pop_screen() else {
+        return Ok(());
+    };
+
+    match prompt.kind {
+        PromptKind::Rule => {
+            app.rule_history = prompt.input.history().to_vec();
+            // The validator already parsed it.
+            let Ok(rule) = text.parse::<Rule>() else {
+                return Ok(());
+            };
+            submit_rule(app, rule)?;
+        }
+        PromptKind::Command => {
+            app.command_history = prompt.input.history().to_vec();
+            // The validator already parsed it.
+            let Ok(command) = parse_command(&text) else {
+                return Ok(());
+            };
+            run_command(app, command)?;
+        }
+        PromptKind::Jump => {
+            // The validator already parsed it.
+            let Ok(index) = text.trim().parse::<usize>() else {
+                return Ok(());
+            };
+            if index >= app.patterns.len() {
+                return Err(ActionError::NoSuchKoan(index));
             }
+            select_pattern(app, index);
         }
-        (_, KeyCode::Char('A')) => {
-            let old_index = app.selected_pattern_index;
-            let old_index_modulo = app.selected_pattern_index % app.patterns_per_row;
-            app.selected_pattern_index = app.selected_pattern_index.saturating_sub(1);
-            if app.selected_pattern_index % app.patterns_per_row > old_index_modulo {
-                app.selected_pattern_index = old_index;
+        PromptKind::Name => set_player_name(app, &text),
+    }
+    Ok(())
+}
+
+/// Does what a palette command says, mostly by pressing the key for it.
+fn run_command(app: &mut App, command: Command) -> Result<(), ActionError> {
+    match command {
+        Command::Key(code) => handle_key(app, KeyEvent::new(code, KeyModifiers::NONE))?,
+        Command::Goto(index) => {
+            if index >= app.patterns.len() {
+                return Err(ActionError::NoSuchKoan(index));
             }
+            select_pattern(app, index);
         }
-        (_, KeyCode::Char('D')) => {
-            let old_index = app.selected_pattern_index;
-            app.selected_pattern_index = app.selected_pattern_index + 1;
-            if app.selected_pattern_index % app.patterns_per_row == 0 {
-                app.selected_pattern_index = old_index;
+        Command::Filter(filter) => set_filter(app, filter),
+        Command::Save(path) => {
+            if let Some(path) = path {
+                app.save_path = path;
             }
+            handle_key(
+                app,
+                KeyEvent::new(KeyCode::Char('s'), KeyModifiers::CONTROL),
+            )?;
+        }
+        Command::Theme(theme) => app.theme = theme,
+        Command::Rows(rows) => {
+            app.pattern_rows = rows;
+            scroll_to_selection(app);
+        }
+        Command::Columns(columns) => {
+            app.patterns_per_row = columns;
+            scroll_to_selection(app);
+        }
+        Command::New => {
+            app.data_big = PatternParent::new(Pattern::default(), true);
+            app.open_editor();
         }
-        (_, KeyCode::Char('W')) => {
-            let old_index = app.selected_pattern_index;
-            let old_index_modulo = app.selected_pattern_index % app.patterns_per_row;
-            app.selected_pattern_index = app
-                .selected_pattern_index
-                .saturating_sub(app.patterns_per_row);
-            if app.selected_pattern_index % app.patterns_per_row != old_index_modulo {
-                app.selected_pattern_index = old_index;
+        Command::Name(name) => {
+            validate_name(&name).map_err(ActionError::Invalid)?;
+            set_player_name(app, &name);
+        }
+        Command::Rule(rule) => submit_rule(app, rule)?,
+        Command::Assistant(enabled) => set_assistant(app, enabled)?,
+        Command::SymmetricRepeats(symmetric) => {
+            app.symmetric_repeats = symmetric;
+            app.duplicate = None;
+        }
+        Command::Keymap(keymap) => {
+            app.keymap = keymap;
+            app.vim = Vim::default();
+            app.notifications.info(format!("Using the {keymap} keys"));
+        }
+        Command::Move(columns, rows) => {
+            let (col, row) = app.current_pos;
+            app.current_pos = (
+                col.saturating_add_signed(columns).min(WIDTH - 1),
+                row.saturating_add_signed(rows).min(HEIGHT - 1),
+            );
+        }
+        Command::Clear => clear_piece(app)?,
+        Command::Yank => yank(app)?,
+        Command::YankKoan => yank_koan(app)?,
+        Command::Put => put(app)?,
+        Command::Select => toggle_selection(app),
+        Command::Set(attributes) => {
+            for attribute in attributes {
+                set_attribute(app, attribute)?;
             }
+        }
+        Command::Transform(transform) => transform_pattern(app, transform)?,
+        Command::Undo => restore_edit(app, false)?,
+        Command::Redo => restore_edit(app, true)?,
+    }
+    Ok(())
+}
 
-            if app.selected_pattern_index < app.render_start_index {
-                step_left(app, app.patterns_per_row);
+/// Removes the piece under the cursor, or every piece in the selection.
+fn clear_piece(app: &mut App) -> Result<(), ActionError> {
+    let pos = app.current_pos;
+    match app.selection.take() {
+        Some(anchor) => app.edit_target(|pattern| {
+            for row in 0..HEIGHT {
+                for col in 0..WIDTH {
+                    if in_rectangle((col, row), anchor, pos) {
+                        pattern.clear((col, row));
+                    }
+                }
             }
+        })?,
+        None => {
+            app.edit_target(|pattern| pattern.clear(pos))?
+                .ok_or(ActionError::EmptyCell)?;
+        }
+    }
+    Ok(())
+}
+
+/// Copies the selection, or the piece under the cursor, for `put`.
+fn yank(app: &mut App) -> Result<(), ActionError> {
+    let pattern = *app.pattern_mut(app.target())?;
+    let pos = app.current_pos;
+    let clip = match app.selection.take() {
+        Some(anchor) => Clip::copy(&pattern, anchor, pos),
+        None if pattern.get(pos).is_none() => return Err(ActionError::EmptyCell),
+        None => Clip::copy(&pattern, pos, pos),
+    };
+    app.notifications.info(format!(
+        "Copied {}x{} cells with {} pieces, p pastes them",
+        clip.width(),
+        clip.height(),
+        clip.pieces()
+    ));
+    app.clip = Some(clip);
+    Ok(())
+}
+
+fn yank_koan(app: &mut App) -> Result<(), ActionError> {
+    let pattern = *app.pattern_mut(app.target())?;
+    app.clip = Some(Clip::whole(&pattern));
+    app.notifications
+        .info("Copied the whole koan, p pastes it over another");
+    Ok(())
+}
+
+/// Pastes the copied cells at the cursor, see `Clip::paste`.
+fn put(app: &mut App) -> Result<(), ActionError> {
+    let clip = app.clip.take().ok_or(ActionError::EmptyClip)?;
+    let pos = app.current_pos;
+    let pasted = app.edit_target(|pattern| clip.paste(pattern, pos));
+    app.clip = Some(clip);
+    pasted
+}
+
+fn transform_pattern(app: &mut App, transform: Transform) -> Result<(), ActionError> {
+    app.edit_target(|pattern| *pattern = transform.apply(pattern))
+}
+
+fn palette_key(key: char) -> Option<Attribute> {
+    PALETTE
+        .iter()
+        .find(|(palette_key, _, _)| *palette_key == key)
+        .map(|(_, _, attribute)| *attribute)
+}
+
+/// Sets an attribute of the palette piece, and of the piece under the cursor
+/// if there is one.
+fn set_attribute(app: &mut App, attribute: Attribute) -> Result<(), ActionError> {
+    attribute.apply(&mut app.brush);
+    let pos = app.current_pos;
+    let target = app.target();
+    if app
+        .pattern_mut(target)
+        .is_ok_and(|pattern| pattern.get(pos).is_some())
+    {
+        app.edit_target(|pattern| pattern.update(pos, |piece| attribute.apply(piece)))?;
+    }
+    Ok(())
+}
+
+/// Turns stamping on, placing the palette piece under the cursor, or off.
+fn toggle_stamp(app: &mut App) -> Result<(), ActionError> {
+    app.stamp = !app.stamp;
+    match app.stamp {
+        true => stamp(app),
+        false => Ok(()),
+    }
+}
+
+fn stamp(app: &mut App) -> Result<(), ActionError> {
+    let (pos, brush) = (app.current_pos, app.brush);
+    app.edit_target(|pattern| pattern.set(pos, brush))?;
+    Ok(())
+}
+
+fn toggle_selection(app: &mut App) {
+    app.selection = match app.selection {
+        Some(_) => None,
+        None => {
+            app.notifications
+                .info("Move to select a block, k copies it, Backspace clears it");
+            Some(app.current_pos)
+        }
+    };
+}
+
+/// Picks up the piece under the cursor, or drops the piece picked up on the
+/// cursor cell, trading places with the piece there.
+fn carry(app: &mut App) -> Result<(), ActionError> {
+    let here = Carried {
+        target: app.target(),
+        pos: app.current_pos,
+    };
+    let Some(from) = app.carried.take() else {
+        app.editable(here.target)?;
+        app.pattern_mut(here.target)?
+            .get(here.pos)
+            .ok_or(ActionError::EmptyCell)?;
+        app.carried = Some(here);
+        app.notifications
+            .info("Picked up the piece, z drops it on another cell, Esc keeps it here");
+        return Ok(());
+    };
+    if from == here {
+        return Ok(());
+    }
+    let piece = app
+        .pattern_mut(from.target)?
+        .get(from.pos)
+        .ok_or(ActionError::EmptyCell)?;
+    let replaced = if from.target == here.target {
+        app.edit_pattern(here.target, |pattern| {
+            let replaced = pattern.set(here.pos, piece);
+            put_or_clear(pattern, from.pos, replaced);
+            replaced
+        })?
+    } else {
+        let replaced = app.edit_pattern(here.target, |pattern| pattern.set(here.pos, piece))?;
+        app.edit_pattern(from.target, |pattern| {
+            put_or_clear(pattern, from.pos, replaced)
+        })?;
+        replaced
+    };
+    if replaced.is_some() {
+        app.notifications.info("Swapped the two pieces");
+    }
+    Ok(())
+}
+
+fn put_or_clear(pattern: &mut Pattern, pos: (usize, usize), piece: Option<Piece>) {
+    match piece {
+        Some(piece) => pattern.set(pos, piece),
+        None => pattern.clear(pos),
+    };
+}
+
+/// Undoes the newest piece change, or redoes the newest undone one, and
+/// selects the koan it was on. Changes that changed nothing are skipped.
+fn restore_edit(app: &mut App, redo: bool) -> Result<(), ActionError> {
+    loop {
+        let edit = match redo {
+            true => app.redone.pop().ok_or(ActionError::NothingToRedo)?,
+            false => app.edits.pop().ok_or(ActionError::NoEditToUndo)?,
+        };
+        let Ok(pattern) = app.pattern_mut(edit.target) else {
+            continue;
+        };
+        if *pattern == edit.pattern {
+            continue;
+        }
+        let before = Edit {
+            target: edit.target,
+            pattern: std::mem::replace(pattern, edit.pattern),
+        };
+        match redo {
+            true => app.edits.push(before),
+            false => app.redone.push(before),
+        }
+        if let Some(index) = edit.target {
+            select_pattern(app, index);
+        }
+        return Ok(());
+    }
+}
+
+/// Guesses the rule in a game, otherwise checks it against the koans and
+/// tells how hard it is to crack. A master checks their own rule.
+fn submit_rule(app: &mut App, rule: Rule) -> Result<(), ActionError> {
+    let guessing = app
+        .game
+        .as_ref()
+        .is_some_and(|game| game.master != app.human_seat);
+    if guessing {
+        play(app, Action::Guess(rule))?;
+    } else {
+        check_rule(app, &rule);
+        rate_rule(app, &rule);
+    }
+    Ok(())
+}
+
+/// Turns the rule assistant on or off. In a running game only the master may,
+/// so guessers can not turn it back on in a competitive game.
+fn set_assistant(app: &mut App, enabled: bool) -> Result<(), ActionError> {
+    let guessing = app
+        .game
+        .as_ref()
+        .is_some_and(|game| !game.is_over() && game.master != app.human_seat);
+    if guessing {
+        return Err(ActionError::NotMaster);
+    }
+    app.assistant_enabled = enabled;
+    if !enabled {
+        app.suggestions.clear();
+    }
+    app.notifications.info(match enabled {
+        true => "The rule assistant is on",
+        false => "The rule assistant is off, for a competitive game",
+    });
+    Ok(())
+}
+
+fn set_player_name(app: &mut App, name: &str) {
+    app.player_name = name.trim().to_string();
+    app.notifications.info(format!(
+        "Your name is {} from the next game on",
+        app.player_name
+    ));
+}
+
+/// Does `action` once the player agrees to `question`, or right away when
+/// `--confirm` left it out.
+fn confirm(app: &mut App, action: ConfirmAction, question: &str) -> Result<(), ActionError> {
+    if !app.confirmations.contains(&action.confirmation()) {
+        return run_confirmed(app, action);
+    }
+    app.push_screen(Screen::Confirm(Confirm {
+        question: question.to_string(),
+        action,
+    }));
+    Ok(())
+}
+
+/// Starts a game, asking first if that ends a running one or loses koans
+/// that were not saved, as starting clears the gallery.
+fn new_game(app: &mut App, action: ConfirmAction) -> Result<(), ActionError> {
+    if app.game_running() {
+        confirm(app, action, "End the running game and start a new one?")
+    } else if app.has_unsaved_changes() {
+        confirm(
+            app,
+            action,
+            "Start a new game? The koans that were not saved are lost.",
+        )
+    } else {
+        run_confirmed(app, action)
+    }
+}
+
+fn on_confirm_key_event(app: &mut App, key: KeyEvent) -> Result<(), ActionError> {
+    match key.code {
+        KeyCode::Char('y') | KeyCode::Enter => {
+            if let Some(Screen::Confirm(confirm)) = app.pop_screen() {
+                run_confirmed(app, confirm.action)?;
+            }
+        }
+        KeyCode::Char('n' | 'q') | KeyCode::Esc => {
+            app.pop_screen();
         }
-        (_, KeyCode::Char('S')) => {
-            let old_index = app.selected_pattern_index;
-            let old_index_modulo = app.selected_pattern_index % app.patterns_per_row;
+        _ => {}
+    }
+    Ok(())
+}
 
-            app.selected_pattern_index = app.selected_pattern_index + app.patterns_per_row;
+fn run_confirmed(app: &mut App, action: ConfirmAction) -> Result<(), ActionError> {
+    match action {
+        ConfirmAction::Quit => app.quit(),
+        ConfirmAction::Overwrite => modify_pattern(app)?,
+        ConfirmAction::Delete => delete_pattern(app)?,
+        ConfirmAction::RevealRules => {
+            app.push_screen(Screen::Solver);
+            app.solver_index = 0;
+        }
+        ConfirmAction::BotMasterGame => start_bot_master_game(app),
+        ConfirmAction::BotGame => {
+            start_game(app, false, &[Strategy::Greedy, Strategy::Conservative])
+        }
+        ConfirmAction::Setup(setup) => start_setup(app, setup),
+    }
+    Ok(())
+}
 
-            if (app.selected_pattern_index % app.patterns_per_row != old_index_modulo)
-                || (app.selected_pattern_index > app.patterns.len().saturating_sub(1))
-            {
-                app.selected_pattern_index = old_index;
+/// Sets up a game: the arrows pick a row and change it, Enter starts.
+fn on_lobby_key_event(app: &mut App, key: KeyEvent) -> Result<(), ActionError> {
+    let Some(Screen::Lobby(lobby)) = app.screens.last_mut() else {
+        return Ok(());
+    };
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => {
+            app.pop_screen();
+        }
+        KeyCode::Up => lobby.row = lobby.row.saturating_sub(1),
+        KeyCode::Down => lobby.row = (lobby.row + 1).min(Lobby::ROWS - 1),
+        KeyCode::Left => lobby.change(-1),
+        KeyCode::Right | KeyCode::Char(' ') => lobby.change(1),
+        KeyCode::Enter => {
+            let setup = lobby.setup;
+            if !setup.bot_master && setup.strategies().is_empty() {
+                return Err(ActionError::Invalid(
+                    "A game needs a guesser, pick a bot to play".to_string(),
+                ));
             }
+            app.pop_screen();
+            new_game(app, ConfirmAction::Setup(setup))?;
+        }
+        _ => {}
+    }
+    Ok(())
+}
 
-            if app.selected_pattern_index > app.render_end_index {
-                step_right(app, app.patterns_per_row);
+/// Steps through the gallery koans in the order they were played.
+fn on_replay_key_event(app: &mut App, key: KeyEvent) {
+    let last = app.patterns.len().saturating_sub(1);
+    let Some(Screen::Replay(replay)) = app.screens.last_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q' | 'F') => {
+            app.pop_screen();
+            return;
+        }
+        KeyCode::Left => replay.index = replay.index.saturating_sub(1),
+        KeyCode::Right => replay.index = (replay.index + 1).min(last),
+        KeyCode::Home => replay.index = 0,
+        KeyCode::End => replay.index = last,
+        KeyCode::Char(' ') => {
+            replay.playing = match replay.playing {
+                Some(_) => None,
+                None => Some(Instant::now()),
             }
         }
+        _ => return,
+    }
+    let index = replay.index;
+    select_pattern(app, index);
+}
+
+/// Steps a playing replay on to the next koan once it showed one long
+/// enough, and stops at the last.
+pub fn play_replay(app: &mut App) {
+    let last = app.patterns.len().saturating_sub(1);
+    let Some(Screen::Replay(replay)) = app.screens.last_mut() else {
+        return;
+    };
+    let Some(shown) = replay.playing else {
+        return;
+    };
+    if shown.elapsed() < REPLAY_STEP {
+        return;
+    }
+    replay.index = (replay.index + 1).min(last);
+    replay.playing = (replay.index < last).then(Instant::now);
+    let index = replay.index;
+    select_pattern(app, index);
+}
+
+/// Tells whether a rule gives every saved koan its verdict, or the first
+/// koan it does not.
+fn check_rule(app: &mut App, rule: &Rule) {
+    let contradiction = app
+        .patterns
+        .iter()
+        .position(|pattern| rule.holds(&pattern.data) != pattern.valid);
+    match contradiction {
+        Some(index) => {
+            app.notifications
+                .warn(format!("\"{rule}\" does not fit koan #{index}"));
+            select_pattern(app, index);
+        }
+        None => app
+            .notifications
+            .info(format!("\"{rule}\" fits all {} koans", app.patterns.len())),
+    }
+}
+
+/// Posts the difficulty estimate, as a warning when it has any.
+fn rate_rule(app: &mut App, rule: &Rule) {
+    let difficulty = estimate(rule, &mut app.rng);
+    let message = format!("\"{rule}\" is {difficulty}");
+    match difficulty.warnings.is_empty() {
+        true => app.notifications.info(message),
+        false => app.notifications.warn(message),
+    }
+}
+
+fn validate_index(text: &str) -> Result<(), String> {
+    match text.trim().parse::<usize>() {
+        Ok(_) => Ok(()),
+        Err(_) => Err("type the number of a koan".to_string()),
+    }
+}
+
+/// Names show up in every game message, so they are kept short.
+fn validate_name(text: &str) -> Result<(), String> {
+    const MAX_NAME_LENGTH: usize = 20;
+    let name = text.trim();
+    if name.is_empty() {
+        Err("the name can not be empty".to_string())
+    } else if name.chars().count() > MAX_NAME_LENGTH {
+        Err(format!(
+            "the name can be at most {MAX_NAME_LENGTH} characters"
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+/// Scrolls the message log, newest at the bottom.
+fn on_log_key_event(app: &mut App, key: KeyEvent) {
+    let oldest = app.notifications.log().len().saturating_sub(1);
+    match key.code {
+        KeyCode::Esc | KeyCode::Char('q' | 'L') => {
+            app.pop_screen();
+        }
+        KeyCode::Up => app.log_scroll = (app.log_scroll + 1).min(oldest),
+        KeyCode::Down => app.log_scroll = app.log_scroll.saturating_sub(1),
         _ => {}
     }
 }
 
-fn step_left(app: &mut App, step_size: usize) {
-    let select_size = app.pattern_rows * app.patterns_per_row;
-    let min_index = select_size.min(app.patterns.len()).saturating_sub(1);
+fn new_suggestions(app: &mut App) {
+    app.suggestion_index = 0;
+    app.suggestions = if app.assistant_enabled {
+        let rules: Vec<_> = app.solver.consistent(&app.patterns).collect();
+        suggest(&rules, &app.patterns, &mut app.rng)
+    } else {
+        vec![]
+    };
+}
 
-    app.render_end_index = (app.render_end_index.saturating_sub(step_size)).max(min_index);
-    app.render_start_index = app.render_end_index.saturating_sub(select_size - 1);
+/// Pasting notation replaces the pattern in the editor. While typing, the
+/// text goes to the prompt or the annotation field instead.
+pub fn on_paste(app: &mut App, text: &str) {
+    match app.screens.last_mut() {
+        Some(Screen::Prompt(prompt)) => {
+            prompt.input.paste(text);
+            return;
+        }
+        Some(Screen::Annotation(draft)) => {
+            draft.fields[draft.field].paste(text);
+            return;
+        }
+        _ => {}
+    }
+    match parse_parent(text) {
+        Ok(pattern) => {
+            app.data_big = pattern;
+            app.open_editor();
+        }
+        Err(err) => app.notifications.error(format!("paste: {err}")),
+    }
+}
+
+/// Koans can be changed, deleted, moved and copied, except while they are the
+/// koans of a running game.
+fn rearrangeable(app: &App) -> Result<(), ActionError> {
+    match app.game_running() {
+        true => Err(ActionError::GameRunning),
+        false => Ok(()),
+    }
+}
+
+fn delete_pattern(app: &mut App) -> Result<(), ActionError> {
+    rearrangeable(app)?;
+    let index = app.selected_pattern_index;
+    if index >= app.patterns.len() {
+        return Err(ActionError::EmptyGallery);
+    }
+    let pattern = app.patterns.remove(index);
+    app.deleted.push((index, pattern));
+    app.forget_indexes();
+    select_pattern(app, index.min(app.patterns.len().saturating_sub(1)));
+    app.notifications
+        .info(format!("Deleted koan #{index}, u brings it back"));
+    Ok(())
+}
+
+fn undo_delete(app: &mut App) -> Result<(), ActionError> {
+    rearrangeable(app)?;
+    let (index, pattern) = app.deleted.pop().ok_or(ActionError::NothingToUndo)?;
+    let index = index.min(app.patterns.len());
+    app.patterns.insert(index, pattern);
+    app.forget_indexes();
+    select_pattern(app, index);
+    app.notifications.info(format!("Restored koan #{index}"));
+    Ok(())
+}
+
+/// Swaps the selected koan with its neighbour, `step` is -1 for left and 1
+/// for right. The koan stays selected.
+fn move_pattern(app: &mut App, step: isize) -> Result<(), ActionError> {
+    rearrangeable(app)?;
+    let index = app.selected_pattern_index;
+    app.selected_pattern()?;
+    let Some(target) = index
+        .checked_add_signed(step)
+        .filter(|target| *target < app.patterns.len())
+    else {
+        return Ok(());
+    };
+    app.patterns.swap(index, target);
+    app.forget_indexes();
+    select_pattern(app, target);
+    Ok(())
 }
 
-fn step_right(app: &mut App, step_size: usize) {
-    let select_size = app.pattern_rows * app.patterns_per_row;
+fn copy_notation(app: &mut App, notation: &str) -> Result<(), ActionError> {
+    copy_to_clipboard(notation).map_err(ActionError::Clipboard)?;
+    app.notifications.info(format!("Copied {notation}"));
+    Ok(())
+}
+
+fn write_export(app: &mut App, path: &str, content: &str) -> Result<(), ActionError> {
+    if !app.write_files {
+        app.notifications.info(format!("Did not write {path}"));
+        return Ok(());
+    }
+    std::fs::write(path, content).map_err(|source| ActionError::Write {
+        path: path.to_string(),
+        source,
+    })?;
+    app.notifications.info(format!("Wrote {path}"));
+    Ok(())
+}
 
-    app.render_end_index = (app.render_end_index + step_size).min(app.patterns.len() - 1);
-    app.render_start_index = app.render_end_index.saturating_sub(select_size - 1);
+fn modify_pattern(app: &mut App) -> Result<(), ActionError> {
+    rearrangeable(app)?;
+    let pattern = app
+        .patterns
+        .get_mut(app.pattern_index)
+        .ok_or(ActionError::EmptyGallery)?;
+    *pattern = app.data_big.clone();
+    app.forget_indexes();
+    Ok(())
 }
 
-fn modify_pattern(app: &mut App) {
-    app.patterns[app.pattern_index] = app.data_big.clone();
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::helpers::rng::Rng;
+    use std::time::Duration;
+
+    fn press(app: &mut App, code: KeyCode) {
+        on_key_event(app, KeyEvent::new(code, KeyModifiers::NONE));
+    }
+
+    #[test]
+    fn holds_back_a_repeated_game_koan_once() {
+        let mut app = App::new();
+        app.rng = Rng::new(0);
+        app.think_delay = Duration::ZERO;
+        start_bot_master_game(&mut app);
+        let opening = app.patterns[0].clone();
+
+        app.data_big = opening.clone();
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.patterns.len(), 2);
+        assert!(app.is_duplicate(0));
+
+        press(&mut app, KeyCode::Enter);
+        assert_eq!(app.patterns.len(), 3);
+        assert_eq!(app.patterns[2].data, opening.data);
+    }
+
+    #[test]
+    fn counts_mirrors_as_repeats_by_the_setting_without_the_assistant() {
+        let mut app = App::new();
+        app.assistant_enabled = false;
+        app.patterns = vec![parse_parent("+ Lr^").unwrap()];
+        let mirror = parse_parent("+ . . Lr^").unwrap();
+
+        app.data_big = mirror.clone();
+        save_pattern(&mut app);
+        assert_eq!(app.patterns.len(), 2);
+
+        app.patterns.pop();
+        app.symmetric_repeats = true;
+        app.data_big = mirror;
+        save_pattern(&mut app);
+        assert_eq!(app.patterns.len(), 1);
+        assert!(app.is_duplicate(0));
+    }
+
+    #[test]
+    fn starts_the_game_the_lobby_sets_up() {
+        let mut app = App::new();
+        app.rng = Rng::new(0);
+        press(&mut app, KeyCode::Char('J'));
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.screen(), Screen::Lobby(_)));
+        assert!(app.game.is_none());
+
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Right);
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Enter);
+        assert!(matches!(app.screen(), Screen::Gallery));
+        let game = app.game.as_ref().unwrap();
+        assert_eq!(game.master, app.human_seat);
+        assert_eq!(game.seats.len(), 3);
+        assert_eq!(game.seats[2].name, "conservative bot");
+        assert_eq!(app.think_delay, Duration::from_millis(300));
+    }
+
+    #[test]
+    fn replays_the_koans_in_order() {
+        let mut app = App::new();
+        app.patterns = ["+ Lr^", "- Sy^", "+ Mb^"]
+            .map(|koan| parse_parent(koan).unwrap())
+            .to_vec();
+        press(&mut app, KeyCode::Char('F'));
+        assert_eq!(app.selected_pattern_index, 0);
+        press(&mut app, KeyCode::End);
+        assert_eq!(app.selected_pattern_index, 2);
+        press(&mut app, KeyCode::Right);
+        assert_eq!(app.selected_pattern_index, 2);
+
+        press(&mut app, KeyCode::Home);
+        press(&mut app, KeyCode::Char(' '));
+        let Some(Screen::Replay(replay)) = app.screens.last_mut() else {
+            panic!("the replay closed");
+        };
+        replay.playing = Some(Instant::now() - REPLAY_STEP);
+        play_replay(&mut app);
+        assert_eq!(app.selected_pattern_index, 1);
+
+        press(&mut app, KeyCode::Esc);
+        assert!(matches!(app.screen(), Screen::Gallery));
+    }
 }
@@ -1 +1,11 @@
+pub mod clip;
+pub mod clipboard;
+pub mod command;
+pub mod fuzz;
+pub mod gallery;
+pub mod game_handler;
 pub mod key_handler;
+pub mod notifications;
+pub mod rng;
+pub mod transform;
+pub mod vim;
@@ -0,0 +1,91 @@
+use std::time::{Duration, Instant};
+
+use ratatui::style::Color;
+use strum_macros::Display;
+
+/// How much a message matters, which sets its color and how long it stays
+/// in the footer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Display)]
+pub enum Severity {
+    Info,
+    Warning,
+    Error,
+}
+
+impl Severity {
+    pub fn color(self) -> Color {
+        match self {
+            Severity::Info => Color::Gray,
+            Severity::Warning => Color::Yellow,
+            Severity::Error => Color::Red,
+        }
+    }
+
+    fn shown_for(self) -> Duration {
+        match self {
+            Severity::Info => Duration::from_secs(6),
+            Severity::Warning | Severity::Error => Duration::from_secs(10),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Notification {
+    pub severity: Severity,
+    pub message: String,
+    pub posted: Instant,
+}
+
+impl Notification {
+    pub fn is_fresh(&self) -> bool {
+        self.posted.elapsed() < self.severity.shown_for()
+    }
+}
+
+/// Older messages are dropped from the log past this many.
+const LOG_LIMIT: usize = 500;
+
+/// Everything that happened, for the footer and the message log. Anything
+/// that wants the players to know something posts here, be it a key action,
+/// a game event or a message from another player.
+#[derive(Debug, Default)]
+pub struct Notifications {
+    log: Vec<Notification>,
+}
+
+impl Notifications {
+    pub fn post(&mut self, severity: Severity, message: impl Into<String>) {
+        if self.log.len() == LOG_LIMIT {
+            self.log.remove(0);
+        }
+        self.log.push(Notification {
+            severity,
+            message: message.into(),
+            posted: Instant::now(),
+        });
+    }
+
+    pub fn info(&mut self, message: impl Into<String>) {
+        self.post(Severity::Info, message);
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.post(Severity::Warning, message);
+    }
+
+    pub fn error(&mut self, message: impl Into<String>) {
+        self.post(Severity::Error, message);
+    }
+
+    /// Messages still shown in the footer, oldest first.
+    pub fn current(&self) -> impl Iterator<Item = &Notification> {
+        self.log
+            .iter()
+            .filter(|notification| notification.is_fresh())
+    }
+
+    /// Every message, oldest first.
+    pub fn log(&self) -> &[Notification] {
+        &self.log
+    }
+}
@@ -0,0 +1,152 @@
+//! Whole-pattern changes, for building variations of a koan: turning and
+//! mirroring the grid, shifting it, and changing every piece's color or size.
+//!
+//! Turning or mirroring the grid turns the pieces with it. A straight piece
+//! looks the same afterwards, an angled one has its solid faces on the other
+//! diagonal, see `Piece::turned`.
+
+use ratatui::style::Color;
+
+use crate::solver::space::SYMMETRIES;
+use crate::{COLOR_BLUE, COLOR_RED, COLOR_YELLOW, HEIGHT, Pattern, PyramidType, SymbolSize, WIDTH};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transform {
+    /// Quarter turns clockwise.
+    Rotate(usize),
+    /// Swaps left and right.
+    MirrorHorizontal,
+    /// Swaps top and bottom.
+    MirrorVertical,
+    /// Moves every piece by columns and rows, wrapping around the edges.
+    Shift(isize, isize),
+    /// Red becomes blue, blue yellow and yellow red, like `c` does.
+    CycleColors,
+    SwapColors(Color, Color),
+    /// Every piece one size larger, large pieces stay large.
+    Grow,
+    Shrink,
+}
+
+impl Transform {
+    pub fn apply(self, pattern: &Pattern) -> Pattern {
+        let mut result = Pattern::default();
+        for pyramid in pattern.iter() {
+            let mut piece = pyramid.piece();
+            let pos = match self {
+                Transform::Rotate(turns) => {
+                    (0..turns % 4).fold(pyramid.pos, |pos, _| SYMMETRIES[1](pos))
+                }
+                Transform::MirrorHorizontal => SYMMETRIES[4](pyramid.pos),
+                Transform::MirrorVertical => SYMMETRIES[5](pyramid.pos),
+                Transform::Shift(columns, rows) => (
+                    wrap(pyramid.pos.0, columns, WIDTH),
+                    wrap(pyramid.pos.1, rows, HEIGHT),
+                ),
+                _ => pyramid.pos,
+            };
+            let odd_turns = matches!(self, Transform::Rotate(turns) if turns % 2 == 1);
+            let mirror = matches!(
+                self,
+                Transform::MirrorHorizontal | Transform::MirrorVertical
+            );
+            if piece.pyramid_type == PyramidType::Angled && (odd_turns || mirror) {
+                piece.turned = !piece.turned;
+            }
+            match self {
+                Transform::CycleColors => piece.color = next_color(piece.color),
+                Transform::SwapColors(a, b) if piece.color == a => piece.color = b,
+                Transform::SwapColors(a, b) if piece.color == b => piece.color = a,
+                Transform::Grow => piece.size = grow(piece.size),
+                Transform::Shrink => piece.size = shrink(piece.size),
+                _ => {}
+            }
+            result.set(pos, piece);
+        }
+        result
+    }
+}
+
+fn wrap(index: usize, step: isize, length: usize) -> usize {
+    let length = length as isize;
+    (index as isize + step.rem_euclid(length)).rem_euclid(length) as usize
+}
+
+fn next_color(color: Color) -> Color {
+    match color {
+        COLOR_RED => COLOR_BLUE,
+        COLOR_BLUE => COLOR_YELLOW,
+        _ => COLOR_RED,
+    }
+}
+
+fn grow(size: SymbolSize) -> SymbolSize {
+    match size {
+        SymbolSize::SMALL => SymbolSize::MEDIUM,
+        SymbolSize::MEDIUM | SymbolSize::LARGE => SymbolSize::LARGE,
+    }
+}
+
+fn shrink(size: SymbolSize) -> SymbolSize {
+    match size {
+        SymbolSize::LARGE => SymbolSize::MEDIUM,
+        SymbolSize::MEDIUM | SymbolSize::SMALL => SymbolSize::SMALL,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::formats::notation::parse_parent;
+
+    fn pattern(notation: &str) -> Pattern {
+        parse_parent(notation).unwrap().data
+    }
+
+    #[test]
+    fn turns_angled_pieces_with_the_grid() {
+        let koan = pattern("Lr/ Sb^");
+        assert_eq!(
+            Transform::Rotate(1).apply(&koan),
+            pattern(". . Lr\\ / . . Sb^")
+        );
+        assert_eq!(
+            Transform::Rotate(2).apply(&koan),
+            pattern("/ . . / . Sb^ Lr/")
+        );
+        assert_eq!(Transform::Rotate(4).apply(&koan), koan);
+    }
+
+    #[test]
+    fn shifts_around_the_edges() {
+        let koan = pattern("Lr/ . Sb^");
+        assert_eq!(Transform::Shift(1, 1).apply(&koan), pattern("/ Sb^ Lr/"));
+        assert_eq!(
+            Transform::Shift(-1, -1).apply(&koan),
+            pattern("/ / . Sb^ Lr/")
+        );
+        assert_eq!(
+            Transform::Shift(isize::MIN, isize::MAX).apply(&koan),
+            Transform::Shift(
+                isize::MIN.rem_euclid(WIDTH as isize),
+                isize::MAX.rem_euclid(HEIGHT as isize)
+            )
+            .apply(&koan)
+        );
+        assert_eq!(Transform::Shift(3, -6).apply(&koan), koan);
+    }
+
+    #[test]
+    fn mirrors_back_to_the_original() {
+        let koan = pattern("Lr/ Sb\\ / . My^");
+        for mirror in [Transform::MirrorHorizontal, Transform::MirrorVertical] {
+            let mirrored = mirror.apply(&koan);
+            assert_ne!(mirrored, koan);
+            assert_eq!(mirror.apply(&mirrored), koan);
+        }
+        assert_eq!(
+            Transform::MirrorHorizontal.apply(&koan),
+            pattern(". Sb/ Lr\\ / . My^")
+        );
+    }
+}
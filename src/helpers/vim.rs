@@ -0,0 +1,75 @@
+//! The vim key preset, a layer over the default keys. It turns vim keys into
+//! palette commands and passes every key it has no meaning for through to
+//! the default keys, so both presets share one set of actions.
+
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+
+use crate::helpers::command::Command;
+
+/// Counts past this are typos, and would take a while to repeat.
+const MAX_COUNT: usize = 99;
+
+/// The keys typed so far of a command that takes more than one, like `3l`
+/// or `dd`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Vim {
+    count: Option<usize>,
+    /// `d` or `y`, waiting for its second press.
+    operator: Option<char>,
+}
+
+impl Vim {
+    /// The commands a key finishes, `None` to let the default keys have it.
+    /// A key that only starts a command gives no commands. With a block
+    /// selected `y` and `d` act on it at once, like in visual mode.
+    pub fn translate(&mut self, key: KeyEvent, selecting: bool) -> Option<Vec<Command>> {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if let (KeyCode::Char(digit @ '0'..='9'), false) = (key.code, ctrl)
+            && (digit != '0' || self.count.is_some())
+            && self.operator.is_none()
+        {
+            let count = self.count.unwrap_or(0) * 10 + digit as usize - '0' as usize;
+            self.count = Some(count.min(MAX_COUNT));
+            return Some(vec![]);
+        }
+
+        let pending = self.count.is_some() || self.operator.is_some();
+        let count = self.count.take().unwrap_or(1);
+        let operator = self.operator.take();
+        let repeat = |command: Command| Some(vec![command; count]);
+        match (operator, key.code) {
+            (Some('d'), KeyCode::Char('d')) => Some(vec![Command::Clear]),
+            (Some('y'), KeyCode::Char('y')) => Some(vec![Command::Yank]),
+            // Anything else after an operator cancels it, like in vim.
+            (Some(_), _) => Some(vec![]),
+            (None, KeyCode::Esc) if pending => Some(vec![]),
+            (None, KeyCode::Char('r')) if ctrl => repeat(Command::Redo),
+            (None, _) if ctrl => None,
+            (None, KeyCode::Char('y')) if selecting => Some(vec![Command::Yank]),
+            (None, KeyCode::Char('d')) if selecting => Some(vec![Command::Clear]),
+            (None, KeyCode::Char('v')) => Some(vec![Command::Select]),
+            (None, KeyCode::Char('Y')) => Some(vec![Command::YankKoan]),
+            (None, KeyCode::Char(operator @ ('d' | 'y'))) => {
+                self.operator = Some(operator);
+                Some(vec![])
+            }
+            (None, KeyCode::Char('h')) => Some(vec![Command::Move(-(count as isize), 0)]),
+            (None, KeyCode::Char('l')) => Some(vec![Command::Move(count as isize, 0)]),
+            (None, KeyCode::Char('k')) => Some(vec![Command::Move(0, -(count as isize))]),
+            (None, KeyCode::Char('j')) => Some(vec![Command::Move(0, count as isize)]),
+            (None, KeyCode::Char('p')) => Some(vec![Command::Put]),
+            (None, KeyCode::Char('u')) => repeat(Command::Undo),
+            (None, _) => None,
+        }
+    }
+
+    /// The keys of an unfinished command, for the footer.
+    pub fn pending(&self) -> String {
+        let count = self
+            .count
+            .map(|count| count.to_string())
+            .unwrap_or_default();
+        let operator = self.operator.map(String::from).unwrap_or_default();
+        format!("{count}{operator}")
+    }
+}
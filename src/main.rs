@@ -1,47 +1,68 @@
 use strum_macros::Display;
+mod cli;
+mod formats;
+mod game;
 mod helpers;
+mod solver;
 mod ui;
+use crate::formats::game_file::gallery_to_game_file;
+use crate::game::guesser::Strategy;
+use crate::game::{Game, Player, SeatId};
+use crate::helpers::clip::{Clip, in_rectangle};
+use crate::helpers::gallery::{Filter, shown_patterns};
+use crate::helpers::game_handler::{GameSetup, run_bots};
 use crate::helpers::key_handler::*;
+use crate::helpers::notifications::Notifications;
+use crate::helpers::rng::Rng;
+use crate::helpers::vim::Vim;
+use crate::solver::{
+    Solver,
+    advisor::Suggestion,
+    find_duplicate,
+    rule::Rule,
+    space::{Code, encode},
+};
 use crate::ui::footer::*;
+use crate::ui::palette::render_palette;
+use crate::ui::text_input::TextInput;
 use color_eyre::Result;
-use crossterm::event::{self, Event, KeyEventKind};
+use crossterm::{
+    event::{
+        self, DisableBracketedPaste, DisableMouseCapture, EnableBracketedPaste, EnableMouseCapture,
+        Event, KeyEventKind,
+    },
+    execute,
+};
 use ratatui::{
     DefaultTerminal, Frame,
     layout::{Constraint, Direction, Flex, Layout, Rect},
-    style::Color,
-    widgets::{Block, Clear},
+    style::{Color, Stylize},
+    text::{Line, Span},
+    widgets::{Block, Clear, Paragraph, Wrap},
 };
-use std::ops::{Deref, DerefMut};
+use std::time::{Duration, Instant};
 
 mod hackerman;
 
 fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let Some(app) = cli::run(&args)? else {
+        return Ok(());
+    };
     let terminal = ratatui::init();
-    let result = App::new().run(terminal);
+    let result = app.run(terminal);
     ratatui::restore();
     result
 }
 
-impl Deref for Pattern {
-    type Target = Vec<Datos>;
-    fn deref(&self) -> &Self::Target {
-        &self.0
-    }
-}
-
-impl DerefMut for Pattern {
-    fn deref_mut(&mut self) -> &mut Self::Target {
-        &mut self.0
-    }
-}
-
 /// The main application which holds the state and logic of the application.
 #[derive(Debug)]
 pub struct App {
     /// Is the application running?
     running: bool,
-    mode: Mode,
+    /// Layers from the bottom up, never empty, see `Screen`.
+    screens: Vec<Screen>,
     current_pos: (usize, usize),
     data_big: PatternParent,
     patterns: Vec<PatternParent>,
@@ -51,15 +72,251 @@ pub struct App {
     patterns_per_row: usize,
     pattern_rows: usize,
     selected_pattern_index: usize,
+    piece_style: PieceStyle,
+    glyphs: GlyphSet,
+    /// Messages for the footer and the message log.
+    notifications: Notifications,
+    /// How many messages the log popup is scrolled up from the newest.
+    log_scroll: usize,
+    solver: Solver,
+    /// The master can turn the rule assistant off for competitive games.
+    assistant_enabled: bool,
+    rng: Rng,
+    suggestions: Vec<Suggestion>,
+    suggestion_index: usize,
+    solver_index: usize,
+    game: Option<Game>,
+    /// Computer seats in `game`, by seat.
+    players: Vec<(SeatId, Box<dyn Player>)>,
+    human_seat: SeatId,
+    /// How long bots wait between moves, so their play can be followed.
+    think_delay: Duration,
+    last_bot_move: Instant,
+    /// Set when the koan being saved repeats a saved one, see `save_pattern`.
+    duplicate: Option<Duplicate>,
+    /// Whether rotations and mirrors of a saved koan count as repeats while
+    /// the rule assistant is off, see `repeated_koan`.
+    symmetric_repeats: bool,
+    /// Where Ctrl-s writes the game.
+    save_path: String,
+    /// Whether saves and exports go to disk, off while fuzzing.
+    write_files: bool,
+    /// Rules typed so far, for Up in the rule prompt.
+    rule_history: Vec<String>,
+    /// The name of the human seat in games.
+    player_name: String,
+    /// Actions that ask before they go ahead.
+    confirmations: Vec<Confirmation>,
+    /// The gallery as last loaded or saved, to tell whether it changed.
+    saved_game: String,
+    /// Deleted koans with the index they had, newest last, for undo.
+    deleted: Vec<(usize, PatternParent)>,
+    filter: Filter,
+    theme: Theme,
+    /// Commands typed into the palette so far, for Up.
+    command_history: Vec<String>,
+    keymap: Keymap,
+    /// Keys typed of an unfinished vim command.
+    vim: Vim,
+    /// The cells copied last, for paste.
+    clip: Option<Clip>,
+    /// The corner a block selection started at, the cursor is the other.
+    selection: Option<(usize, usize)>,
+    /// The piece picked up to move, see `Carried`.
+    carried: Option<Carried>,
+    /// Piece changes, newest last, for undo.
+    edits: Vec<Edit>,
+    /// Undone piece changes, newest last, for redo.
+    redone: Vec<Edit>,
+    /// The piece the palette is set to, which `r` and stamping place.
+    brush: Piece,
+    /// Whether moving the cursor places the brush piece.
+    stamp: bool,
+    /// The terminal area drawn last, to tell what a mouse click hit.
+    frame_area: Rect,
 }
 
-#[derive(Debug, Clone)]
-pub struct Pattern(pub Vec<Datos>);
+/// Where a piece picked up in move mode is. It stays there until it is
+/// dropped on another cell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Carried {
+    /// The gallery koan it is on, `None` for the editor koan.
+    target: Option<usize>,
+    pos: (usize, usize),
+}
 
+/// A piece change, with the pattern as it was before it.
 #[derive(Debug, Clone)]
+struct Edit {
+    /// The gallery koan changed, `None` for the editor koan.
+    target: Option<usize>,
+    pattern: Pattern,
+}
+
+/// A saved koan the one in the editor repeats.
+#[derive(Debug, Clone, Copy)]
+struct Duplicate {
+    index: usize,
+    /// The editor koan the warning was given for, saving it again allows it.
+    code: Code,
+    /// Only a rotation or mirror of it matched.
+    rotated: bool,
+}
+
+impl Duplicate {
+    fn how(&self) -> &'static str {
+        match self.rotated {
+            true => "a rotation or mirror of",
+            false => "the same as",
+        }
+    }
+}
+
+/// The pieces on the grid, one cell per position, so a cell can not hold two
+/// pieces and the order pieces were placed in does not matter.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Pattern {
+    cells: [[Option<Piece>; WIDTH]; HEIGHT],
+}
+
+impl Pattern {
+    /// The piece at `(col, row)`, `None` for an empty cell or a position off
+    /// the grid.
+    pub fn get(&self, (col, row): (usize, usize)) -> Option<Piece> {
+        *self.cells.get(row)?.get(col)?
+    }
+
+    /// Like `get`, with the position attached.
+    pub fn pyramid_at(&self, pos: (usize, usize)) -> Option<Datos> {
+        self.get(pos).map(|piece| piece.at(pos))
+    }
+
+    /// Puts a piece on a cell and returns the piece it replaced. A position
+    /// off the grid is left alone and gives `None`, like an empty cell.
+    pub fn set(&mut self, (col, row): (usize, usize), piece: Piece) -> Option<Piece> {
+        self.cells.get_mut(row)?.get_mut(col)?.replace(piece)
+    }
+
+    /// Empties a cell and returns the piece that was on it, `None` for an
+    /// empty cell or a position off the grid.
+    pub fn clear(&mut self, (col, row): (usize, usize)) -> Option<Piece> {
+        self.cells.get_mut(row)?.get_mut(col)?.take()
+    }
+
+    /// Changes the piece on a cell in place and returns it, or `None` when the
+    /// cell is empty.
+    pub fn update(
+        &mut self,
+        (col, row): (usize, usize),
+        change: impl FnOnce(&mut Piece),
+    ) -> Option<Piece> {
+        let piece = self.cells.get_mut(row)?.get_mut(col)?.as_mut()?;
+        change(piece);
+        Some(*piece)
+    }
+
+    /// Places a piece where it says it goes.
+    pub fn place(&mut self, pyramid: Datos) -> Option<Piece> {
+        self.set(pyramid.pos, pyramid.piece())
+    }
+
+    /// The pieces with their positions, row by row.
+    pub fn iter(&self) -> impl Iterator<Item = Datos> + '_ {
+        self.cells.iter().enumerate().flat_map(|(row, cells)| {
+            cells
+                .iter()
+                .enumerate()
+                .filter_map(move |(col, cell)| cell.map(|piece| piece.at((col, row))))
+        })
+    }
+
+    /// Number of pieces on the grid.
+    pub fn len(&self) -> usize {
+        self.cells.iter().flatten().flatten().count()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct PatternParent {
     data: Pattern,
     valid: bool,
+    annotation: Annotation,
+}
+
+impl PatternParent {
+    pub fn new(data: Pattern, valid: bool) -> Self {
+        Self {
+            data,
+            valid,
+            annotation: Annotation::default(),
+        }
+    }
+}
+
+/// The annotation popup: the koan it edits and the text typed so far.
+#[derive(Debug, Clone)]
+struct AnnotationDraft {
+    /// A gallery index, or `None` for the koan in the editor.
+    target: Option<usize>,
+    /// Title, note and tags.
+    fields: [TextInput; 3],
+    field: usize,
+}
+
+impl AnnotationDraft {
+    fn new(target: Option<usize>, annotation: &Annotation) -> Self {
+        Self {
+            target,
+            fields: [
+                TextInput::new(&annotation.title),
+                TextInput::new(&annotation.note),
+                TextInput::new(&annotation.tags.join(" ")),
+            ],
+            field: 0,
+        }
+    }
+}
+
+/// A one line question at the bottom of the screen.
+#[derive(Debug, Clone)]
+struct Prompt {
+    kind: PromptKind,
+    input: TextInput,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum PromptKind {
+    /// A rule to guess in a game, or to check against the gallery.
+    Rule,
+    Name,
+    /// The number of a koan to select.
+    Jump,
+    /// A palette command, see `helpers::command`.
+    Command,
+}
+
+impl PromptKind {
+    fn label(self) -> &'static str {
+        match self {
+            PromptKind::Rule => "rule: ",
+            PromptKind::Name => "name: ",
+            PromptKind::Jump => "koan #",
+            PromptKind::Command => ":",
+        }
+    }
+}
+
+/// What the players wrote down about a koan, to follow their reasoning later.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Annotation {
+    pub title: String,
+    /// Free text, e.g. "testing whether size matters".
+    pub note: String,
+    pub tags: Vec<String>,
 }
 
 // #[derive(Debug, Clone)]
@@ -74,13 +331,252 @@ enum PyramidType {
     Angled,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Display)]
-enum Mode {
-    VIEWING,
-    EDITING,
+/// A layer of the screen. The gallery is always at the bottom of the stack,
+/// every other layer is drawn over the ones below it and the top one gets the
+/// keys.
+#[derive(Debug, Clone, Display)]
+enum Screen {
+    Gallery,
+    /// The koan being built, over the gallery.
+    Editor,
+    Help,
+    /// The rules still possible.
+    Solver,
+    Advisor,
+    Log,
+    Annotation(Box<AnnotationDraft>),
+    /// A line of text, e.g. a rule to guess.
+    Prompt(Prompt),
+    Confirm(Confirm),
+    /// Setting up a game before starting it.
+    Lobby(Lobby),
+    /// The gallery koans one at a time, in the order they were played.
+    Replay(Replay),
 }
 
+/// The game setup popup: who is master, which bots play and how fast.
 #[derive(Debug, Clone, Copy)]
+struct Lobby {
+    setup: GameSetup,
+    /// The row the arrows change: the master, a row per bot strategy, then
+    /// the think delay.
+    row: usize,
+}
+
+impl Lobby {
+    const ROWS: usize = Strategy::ALL.len() + 2;
+
+    fn new(think_delay: Duration) -> Self {
+        Self {
+            setup: GameSetup {
+                bot_master: true,
+                bots: [false; Strategy::ALL.len()],
+                think_delay,
+            },
+            row: 0,
+        }
+    }
+
+    /// Changes the selected row, `step` is -1 for left and 1 for right.
+    fn change(&mut self, step: isize) {
+        let setup = &mut self.setup;
+        match self.row {
+            0 => setup.bot_master = !setup.bot_master,
+            row if row <= Strategy::ALL.len() => setup.bots[row - 1] = !setup.bots[row - 1],
+            _ => {
+                let last = THINK_DELAYS.len() - 1;
+                let index = THINK_DELAYS
+                    .iter()
+                    .position(|delay| *delay >= setup.think_delay)
+                    .unwrap_or(last);
+                let index = index.saturating_add_signed(step).min(last);
+                setup.think_delay = THINK_DELAYS[index];
+            }
+        }
+    }
+
+    fn lines(&self) -> Vec<String> {
+        let setup = &self.setup;
+        let master = match setup.bot_master {
+            true => "the computer",
+            false => "you",
+        };
+        let mut lines = vec![format!("{:>18}  {master}", "master")];
+        for (strategy, plays) in Strategy::ALL.iter().zip(setup.bots) {
+            let plays = if plays { "plays" } else { "-" };
+            lines.push(format!("{:>18}  {plays}", format!("{strategy} bot")));
+        }
+        lines.push(format!(
+            "{:>18}  {} ms between moves",
+            "bots think",
+            setup.think_delay.as_millis()
+        ));
+        lines
+    }
+}
+
+/// Where the replay is, see `Screen::Replay`.
+#[derive(Debug, Clone, Copy)]
+struct Replay {
+    index: usize,
+    /// When the replay last stepped, while it steps on its own.
+    playing: Option<Instant>,
+}
+
+/// A yes or no question before doing something that can not be undone.
+#[derive(Debug, Clone)]
+struct Confirm {
+    question: String,
+    action: ConfirmAction,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ConfirmAction {
+    Quit,
+    /// Replace the gallery koan at `pattern_index` with the editor koan.
+    Overwrite,
+    /// Delete the selected gallery koan.
+    Delete,
+    /// Show the rules still possible during a game.
+    RevealRules,
+    /// Start a game against a computer master, ending the running one.
+    BotMasterGame,
+    /// Start a game as master against two bots, ending the running one.
+    BotGame,
+    /// Start the game set up in the lobby, ending the running one.
+    Setup(GameSetup),
+}
+
+impl ConfirmAction {
+    fn confirmation(self) -> Confirmation {
+        match self {
+            ConfirmAction::Quit => Confirmation::Quit,
+            ConfirmAction::Overwrite => Confirmation::Overwrite,
+            ConfirmAction::Delete => Confirmation::Delete,
+            ConfirmAction::RevealRules => Confirmation::Reveal,
+            ConfirmAction::BotMasterGame | ConfirmAction::BotGame | ConfirmAction::Setup(_) => {
+                Confirmation::NewGame
+            }
+        }
+    }
+}
+
+/// The kinds of action that can ask first, chosen with `--confirm`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Confirmation {
+    /// Quitting with koans that were not saved.
+    Quit,
+    Overwrite,
+    Delete,
+    Reveal,
+    /// Starting a game while one is running, or with koans that were not
+    /// saved.
+    NewGame,
+}
+
+impl Confirmation {
+    pub const ALL: [Confirmation; 5] = [
+        Confirmation::Quit,
+        Confirmation::Overwrite,
+        Confirmation::Delete,
+        Confirmation::Reveal,
+        Confirmation::NewGame,
+    ];
+
+    pub fn parse(name: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|confirmation| confirmation.to_string() == name)
+    }
+}
+
+impl std::fmt::Display for Confirmation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Confirmation::Quit => "quit",
+            Confirmation::Overwrite => "overwrite",
+            Confirmation::Delete => "delete",
+            Confirmation::Reveal => "reveal",
+            Confirmation::NewGame => "new-game",
+        };
+        f.write_str(name)
+    }
+}
+
+/// Which keys the gallery and the editor listen to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Keymap {
+    Default,
+    /// `hjkl`, counts and operators, see `helpers::vim`.
+    Vim,
+}
+
+impl Keymap {
+    pub fn parse(name: &str) -> Option<Self> {
+        [Keymap::Default, Keymap::Vim]
+            .into_iter()
+            .find(|keymap| keymap.to_string() == name)
+    }
+}
+
+impl std::fmt::Display for Keymap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Keymap::Default => "default",
+            Keymap::Vim => "vim",
+        })
+    }
+}
+
+/// The colors pieces are drawn in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+enum Theme {
+    Color,
+    /// Shades of gray, for terminals and eyes that tell colors apart badly.
+    Mono,
+}
+
+/// How the pieces in the gallery are drawn.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+enum PieceStyle {
+    Full,
+    Compact,
+}
+
+/// Which characters the compact renderer may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Display)]
+pub enum GlyphSet {
+    Unicode,
+    Ascii,
+}
+
+impl GlyphSet {
+    /// Guess whether the terminal font has the wedge glyphs. The linux console
+    /// and non UTF-8 locales do not, and `ZENDUI_GLYPHS=ascii|unicode` overrides.
+    fn detect() -> Self {
+        let var = |name: &str| std::env::var(name).unwrap_or_default().to_lowercase();
+
+        match var("ZENDUI_GLYPHS").as_str() {
+            "ascii" => return GlyphSet::Ascii,
+            "unicode" => return GlyphSet::Unicode,
+            _ => (),
+        }
+
+        let locale = [var("LC_ALL"), var("LC_CTYPE"), var("LANG")]
+            .into_iter()
+            .find(|value| !value.is_empty())
+            .unwrap_or_default();
+
+        if var("TERM") == "linux" || !(locale.contains("utf-8") || locale.contains("utf8")) {
+            GlyphSet::Ascii
+        } else {
+            GlyphSet::Unicode
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[allow(clippy::upper_case_acronyms)]
 enum SymbolSize {
     SMALL,
     MEDIUM,
@@ -88,21 +584,152 @@ enum SymbolSize {
 }
 
 fn popup_area(area: Rect, length: u16) -> Rect {
-    let vertical = Layout::vertical([Constraint::Length(length)]).flex(Flex::Center);
-    let horizontal = Layout::horizontal([Constraint::Length(length * 2)]).flex(Flex::Center);
+    centered_area(area, length * 2, length)
+}
+
+fn centered_area(area: Rect, width: u16, height: u16) -> Rect {
+    let vertical = Layout::vertical([Constraint::Length(height)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Constraint::Length(width)]).flex(Flex::Center);
     let [area] = vertical.areas(area);
     let [area] = horizontal.areas(area);
     area
 }
 
-#[derive(Debug, Clone)]
+/// A pyramid, without a place on the grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Piece {
+    pyramid_type: PyramidType,
+    color: Color,
+    size: SymbolSize,
+    /// Angled pieces only: a quarter turn from the usual look, with the solid
+    /// faces top right and bottom left instead of top left and bottom right.
+    turned: bool,
+}
+
+impl Piece {
+    pub fn at(self, pos: (usize, usize)) -> Datos {
+        Datos {
+            pos,
+            pyramid_type: self.pyramid_type,
+            color: self.color,
+            size: self.size,
+            turned: self.turned,
+        }
+    }
+}
+
+/// A pyramid on the grid, as handed out by `Pattern::iter`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct Datos {
     pos: (usize, usize),
     pyramid_type: PyramidType,
     color: Color,
     size: SymbolSize,
+    turned: bool,
 }
 
+impl Datos {
+    pub fn piece(&self) -> Piece {
+        Piece {
+            pyramid_type: self.pyramid_type,
+            color: self.color,
+            size: self.size,
+            turned: self.turned,
+        }
+    }
+}
+
+/// How often the screen is redrawn while waiting for input, e.g. for bots.
+const TICK: Duration = Duration::from_millis(100);
+
+const DEFAULT_THINK_DELAY: Duration = Duration::from_millis(600);
+
+/// The think delays the lobby offers.
+const THINK_DELAYS: [Duration; 5] = [
+    Duration::ZERO,
+    Duration::from_millis(300),
+    DEFAULT_THINK_DELAY,
+    Duration::from_millis(1200),
+    Duration::from_millis(2400),
+];
+
+/// How long a replay shows each koan while it plays on its own.
+const REPLAY_STEP: Duration = Duration::from_millis(1500);
+
+const DEFAULT_SAVE_PATH: &str = "zendui-game.zendo";
+
+const DEFAULT_PLAYER_NAME: &str = "You";
+
+/// The keys of the gallery and the editor, for the help popup.
+const HELP: &[(&str, &str)] = &[
+    ("arrows", "move the cursor on the grid"),
+    ("r c s", "place the palette piece or turn, recolor, resize"),
+    ("Backspace", "remove the piece, or the selected ones"),
+    ("b", "select a block of cells, Esc ends it"),
+    ("1 2 3", "palette: red, blue, yellow, or click it"),
+    ("4 5 6", "palette: small, medium, large"),
+    ("7 8", "palette: straight, angled"),
+    ("f", "stamp the palette piece on every cell moved to"),
+    ("w H V", "turn the koan, mirror it left-right, top-bottom"),
+    ("Ctrl-arrows", "shift the koan, wrapping around"),
+    ("Ctrl-z Ctrl-y", "undo, redo a piece change"),
+    ("P", "recolor every piece, red to blue to yellow"),
+    ("", ":swap red blue, :grow and :shrink change every piece"),
+    ("k K p", "copy the piece or block, the koan, paste"),
+    ("z", "pick up a piece, drop it on another cell"),
+    ("i", "flip the verdict"),
+    ("Enter", "save the editor koan, or play it in a game"),
+    ("", ":repeats symmetric counts turned koans as repeats"),
+    ("e v", "open, close the editor"),
+    ("W A S D", "select a saved koan"),
+    ("a d", "scroll the gallery a row"),
+    ("PgUp PgDn", "select a page up or down"),
+    ("Home End j", "select the first, the last, koan number"),
+    ("m", "replace the selected koan with the editor koan"),
+    ("Delete u", "delete the selected koan, undo the delete"),
+    ("[ ]", "move the selected koan left, right"),
+    ("C", "duplicate the selected koan"),
+    ("l", "load the selected koan into the editor"),
+    (", . < >", "more or fewer columns and rows"),
+    ("g G", "compact pieces, ascii glyphs"),
+    ("t", "annotate the koan"),
+    ("R", "type a rule to guess, or check and rate"),
+    ("N", "set your name"),
+    ("?", "rules still possible"),
+    ("", ":assistant off hides them, the master's call in a game"),
+    ("n", "suggest a koan"),
+    ("M B", "play against a bot master, or as master"),
+    ("J", "set up a game: the master, the bots and their speed"),
+    ("F", "replay the koans in order"),
+    ("Y", "accept the koan to judge"),
+    ("y", "copy the koan as notation"),
+    ("x X o O", "export svg and text"),
+    ("Ctrl-s", "save the game"),
+    ("L", "message log"),
+    (":", "type a command, e.g. goto 3, filter valid, keys vim"),
+    ("q Esc", "quit, Esc closes the editor first"),
+    ("", "asks first if koans are not saved, see --confirm"),
+];
+
+/// Piece changes kept for undo.
+const MAX_EDITS: usize = 100;
+
+/// The keys the vim preset changes, listed above `HELP` with it.
+const VIM_HELP: &[(&str, &str)] = &[
+    ("h j k l", "move the cursor on the grid, 3l moves three"),
+    ("dd", "remove the piece"),
+    ("yy p", "yank the piece, put it on another cell or koan"),
+    ("v", "select a block, y and d yank and remove it"),
+    ("Y", "yank the whole koan"),
+    (":set", "set the palette, e.g. :set red large, or click it"),
+    ("u Ctrl-r", "undo, redo a piece change"),
+    (":w :q", "save the game, quit"),
+    (":help", "this list"),
+    (":undo", "bring back a deleted koan"),
+    (":keys default", "back to the default keys"),
+    ("", "the other keys work as below"),
+];
+
 const HEIGHT: usize = 3;
 const WIDTH: usize = 3;
 
@@ -125,21 +752,31 @@ const COLOR_BLUE_ACTIVE: Color = Color::Rgb(35, 47, 173);
 const COLOR_YELLOW: Color = Color::Rgb(235, 211, 117);
 const COLOR_YELLOW_ACTIVE: Color = Color::Rgb(245, 200, 24);
 
+const MONO_BLUE: Color = Color::Rgb(120, 120, 120);
+const MONO_RED: Color = Color::Rgb(175, 175, 175);
+const MONO_YELLOW: Color = Color::Rgb(235, 235, 235);
+
 const PATTERN_BORDER_VALID: Color = Color::Rgb(55, 150, 55);
 const PATTERN_BORDER_VALID_ACTIVE: Color = Color::Rgb(0, 255, 0);
 const PATTERN_BORDER_INVALID: Color = Color::Rgb(150, 55, 55);
 const PATTERN_BORDER_INVALID_ACTIVE: Color = Color::Rgb(255, 0, 0);
+/// Appended to the title of the saved koan the editor koan repeats.
+const DUPLICATE_MARK: &str = " same as editor";
+const PATTERN_BORDER_DUPLICATE: Color = Color::Rgb(255, 165, 0);
+
+impl Default for App {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 impl App {
     pub fn new() -> Self {
         Self {
             running: true,
-            mode: Mode::VIEWING,
+            screens: vec![Screen::Gallery],
             current_pos: (0, 0),
-            data_big: PatternParent {
-                data: Pattern(vec![]),
-                valid: true,
-            },
+            data_big: PatternParent::new(Pattern::default(), true),
             render_start_index: 0,
             render_end_index: 0,
             pattern_rows: 2,
@@ -147,6 +784,48 @@ impl App {
             patterns: vec![],
             pattern_index: 0,
             selected_pattern_index: 0,
+            piece_style: PieceStyle::Full,
+            glyphs: GlyphSet::detect(),
+            notifications: Notifications::default(),
+            log_scroll: 0,
+            solver: Solver::new(),
+            assistant_enabled: true,
+            rng: Rng::from_time(),
+            suggestions: vec![],
+            suggestion_index: 0,
+            solver_index: 0,
+            game: None,
+            players: vec![],
+            human_seat: 0,
+            think_delay: DEFAULT_THINK_DELAY,
+            last_bot_move: Instant::now(),
+            duplicate: None,
+            symmetric_repeats: false,
+            save_path: DEFAULT_SAVE_PATH.to_string(),
+            write_files: true,
+            rule_history: vec![],
+            player_name: DEFAULT_PLAYER_NAME.to_string(),
+            confirmations: Confirmation::ALL.to_vec(),
+            saved_game: gallery_to_game_file(&[]),
+            deleted: vec![],
+            filter: Filter::All,
+            theme: Theme::Color,
+            command_history: vec![],
+            keymap: Keymap::Default,
+            vim: Vim::default(),
+            clip: None,
+            selection: None,
+            carried: None,
+            edits: vec![],
+            redone: vec![],
+            brush: Piece {
+                pyramid_type: PyramidType::Straight,
+                color: COLOR_BLUE,
+                size: SymbolSize::MEDIUM,
+                turned: false,
+            },
+            stamp: false,
+            frame_area: Rect::default(),
         }
     }
 
@@ -154,27 +833,265 @@ impl App {
         &self.data_big.data
     }
 
-    pub fn active_viewed_pattern(&self) -> &Pattern {
-        let pattern = self.patterns.get(self.selected_pattern_index).unwrap();
-        let actual_pattern = &pattern.data;
-        return actual_pattern;
+    /// Changes the pattern piece edits apply to: the editor koan while
+    /// editing, otherwise the selected gallery koan. See `edit_pattern`.
+    fn edit_target<T>(&mut self, change: impl FnOnce(&mut Pattern) -> T) -> Result<T, ActionError> {
+        self.edit_pattern(self.target(), change)
+    }
+
+    /// `None` for the editor koan, otherwise the selected gallery koan, see
+    /// `edit_target`.
+    fn target(&self) -> Option<usize> {
+        (!self.editing()).then_some(self.selected_pattern_index)
+    }
+
+    /// Changes a pattern, see `pattern_mut`, and keeps it as it was for undo.
+    /// A change that changes nothing is not kept and leaves redo alone.
+    fn edit_pattern<T>(
+        &mut self,
+        target: Option<usize>,
+        change: impl FnOnce(&mut Pattern) -> T,
+    ) -> Result<T, ActionError> {
+        self.editable(target)?;
+        let pattern = self.pattern_mut(target)?;
+        let before = *pattern;
+        let result = change(pattern);
+        if *pattern != before {
+            self.edits.push(Edit {
+                target,
+                pattern: before,
+            });
+            if self.edits.len() > MAX_EDITS {
+                self.edits.remove(0);
+            }
+            self.redone.clear();
+        }
+        Ok(result)
+    }
+
+    /// Gallery koans are the koans of the running game, so only the editor
+    /// koan may change while one runs.
+    fn editable(&self, target: Option<usize>) -> Result<(), ActionError> {
+        match target.is_some() && self.game_running() {
+            true => Err(ActionError::GameRunning),
+            false => Ok(()),
+        }
+    }
+
+    /// The editor koan for `None`, otherwise a gallery koan.
+    fn pattern_mut(&mut self, target: Option<usize>) -> Result<&mut Pattern, ActionError> {
+        match target {
+            None => Ok(&mut self.data_big.data),
+            Some(index) => self
+                .patterns
+                .get_mut(index)
+                .map(|parent| &mut parent.data)
+                .ok_or(ActionError::EmptyGallery),
+        }
+    }
+
+    /// Drops what points at gallery koans by index, after koans were added
+    /// in between, removed or moved.
+    fn forget_indexes(&mut self) {
+        self.duplicate = None;
+        self.edits.retain(|edit| edit.target.is_none());
+        self.redone.retain(|edit| edit.target.is_none());
+        self.carried = self.carried.filter(|carried| carried.target.is_none());
+    }
+
+    /// The saved koan the editor koan repeats, if any. Rotations and mirrors
+    /// count once every rule still possible ignores position. With the rule
+    /// assistant off that would give the rules away, so `symmetric_repeats`
+    /// decides instead.
+    fn repeated_koan(&self) -> Option<Duplicate> {
+        let symmetric = match self.assistant_enabled {
+            true => self
+                .solver
+                .consistent(&self.patterns)
+                .all(Rule::ignores_position),
+            false => self.symmetric_repeats,
+        };
+        let pattern = &self.data_big.data;
+        find_duplicate(pattern, &self.patterns, symmetric).map(|(index, rotated)| Duplicate {
+            index,
+            code: encode(pattern),
+            rotated,
+        })
+    }
+
+    /// Whether quitting now would lose koans: the gallery changed since it
+    /// was loaded or saved, or the editor holds pieces.
+    fn has_unsaved_changes(&self) -> bool {
+        gallery_to_game_file(&self.patterns) != self.saved_game || !self.data_big.data.is_empty()
+    }
+
+    fn game_running(&self) -> bool {
+        self.game.as_ref().is_some_and(|game| !game.is_over())
+    }
+
+    /// The layer that gets the keys.
+    fn screen(&self) -> &Screen {
+        self.screens.last().unwrap_or(&Screen::Gallery)
+    }
+
+    fn push_screen(&mut self, screen: Screen) {
+        self.screens.push(screen);
+    }
+
+    /// Closes the top layer. The gallery at the bottom stays.
+    fn pop_screen(&mut self) -> Option<Screen> {
+        if self.screens.len() > 1 {
+            self.screens.pop()
+        } else {
+            None
+        }
+    }
+
+    /// Whether the editor is open, even with a popup over it.
+    fn editing(&self) -> bool {
+        self.screens
+            .iter()
+            .any(|screen| matches!(screen, Screen::Editor))
+    }
+
+    /// Opens the editor right over the gallery, below any open popups.
+    fn open_editor(&mut self) {
+        if !self.editing() {
+            self.screens.insert(1, Screen::Editor);
+        }
+    }
+
+    /// Closes the editor and everything over it.
+    fn close_editor(&mut self) {
+        if let Some(index) = self
+            .screens
+            .iter()
+            .position(|screen| matches!(screen, Screen::Editor))
+        {
+            self.screens.truncate(index);
+        }
+    }
+
+    /// The selected gallery pattern.
+    fn selected_pattern(&self) -> Result<&PatternParent, ActionError> {
+        self.patterns
+            .get(self.selected_pattern_index)
+            .ok_or(ActionError::EmptyGallery)
     }
 
     /// Run the application's main loop.
     pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
         self.running = true;
+        execute!(std::io::stdout(), EnableBracketedPaste, EnableMouseCapture)?;
         while self.running {
             terminal.draw(|frame| self.render(frame))?;
-            self.handle_crossterm_events()?;
+            if event::poll(TICK)? {
+                self.handle_crossterm_events()?;
+            }
+            run_bots(&mut self);
+            play_replay(&mut self);
         }
+        execute!(
+            std::io::stdout(),
+            DisableBracketedPaste,
+            DisableMouseCapture
+        )?;
         Ok(())
     }
 
-    fn render_footer(&mut self, frame: &mut Frame, layout: Rect, extra: String) {
-        render_footer(self, frame, layout, extra);
+    fn render(&mut self, frame: &mut Frame) {
+        self.frame_area = frame.area();
+        for screen in &self.screens {
+            self.render_screen(frame, screen);
+        }
     }
 
-    fn render(&mut self, frame: &mut Frame) {
+    fn render_screen(&self, frame: &mut Frame, screen: &Screen) {
+        match screen {
+            Screen::Gallery => self.render_gallery(frame),
+            Screen::Editor => {
+                let area = popup_area(frame.area(), 34);
+                frame.render_widget(Clear, area);
+                let first_shown = shown_patterns(self)
+                    .get(self.render_start_index)
+                    .copied()
+                    .unwrap_or(0);
+                self.render_pattern(frame, area, self.data_big.clone(), first_shown);
+                render_palette(self, frame);
+            }
+            Screen::Help => self.render_help(frame),
+            Screen::Solver => self.render_solver(frame),
+            Screen::Advisor => self.render_advisor(frame),
+            Screen::Log => self.render_log(frame),
+            Screen::Annotation(draft) => self.render_annotation(frame, draft),
+            Screen::Prompt(prompt) => self.render_prompt(frame, prompt),
+            Screen::Confirm(confirm) => self.render_confirm(frame, confirm),
+            Screen::Lobby(lobby) => self.render_lobby(frame, lobby),
+            Screen::Replay(replay) => self.render_replay(frame, replay),
+        }
+    }
+
+    fn render_lobby(&self, frame: &mut Frame, lobby: &Lobby) {
+        let area = centered_area(frame.area(), 60, Lobby::ROWS as u16 + 4);
+        let mut lines: Vec<Line> = lobby
+            .lines()
+            .into_iter()
+            .enumerate()
+            .map(|(row, text)| match row == lobby.row {
+                true => Line::from(text).reversed(),
+                false => Line::from(text),
+            })
+            .collect();
+        lines.push(Line::from(""));
+        lines.push(Line::from(
+            "Up/Down: pick  Left/Right: change  Enter: start  Esc: close",
+        ));
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("New game")),
+            area,
+        );
+    }
+
+    fn render_replay(&self, frame: &mut Frame, replay: &Replay) {
+        let Some(pattern) = self.patterns.get(replay.index) else {
+            return;
+        };
+        let area = centered_area(frame.area(), 64, COMPACT_CELL_HEIGHT * HEIGHT as u16 + 4);
+        let block = Block::bordered().title(format!(
+            "Replay {}/{}",
+            replay.index + 1,
+            self.patterns.len()
+        ));
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        let [koan_area, _, text_area] = Layout::horizontal([
+            Constraint::Length(COMPACT_CELL_WIDTH * WIDTH as u16 + 2),
+            Constraint::Length(2),
+            Constraint::Fill(1),
+        ])
+        .areas(inner);
+        let title = self.pattern_title(replay.index);
+        self.render_pattern_compact(frame, koan_area, pattern, &title, false, false);
+
+        let verdict = if pattern.valid { "valid" } else { "invalid" };
+        let playing = match replay.playing {
+            Some(_) => "Space: pause",
+            None => "Space: play",
+        };
+        let text = vec![
+            Line::from(verdict),
+            Line::from(pattern.annotation.note.as_str()),
+            Line::from(""),
+            Line::from(format!("Left/Right: step  {playing}")),
+            Line::from("Home/End: first, last  Esc: close"),
+        ];
+        frame.render_widget(Paragraph::new(text).wrap(Wrap { trim: true }), text_area);
+    }
+
+    fn render_gallery(&self, frame: &mut Frame) {
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([Constraint::Fill(1), Constraint::Length(2)])
@@ -189,12 +1106,13 @@ impl App {
             .constraints((0..self.pattern_rows).map(|_| Constraint::Fill(1)))
             .split(inner_layout[0]);
 
-        let render_end_index2 = (self.render_end_index + 1).min(self.patterns.len());
+        let shown = shown_patterns(self);
+        let render_end_index2 = (self.render_end_index + 1).min(shown.len());
         let patterns_to_render =
-            self.patterns.clone()[self.render_start_index..render_end_index2].to_vec();
+            &shown[self.render_start_index.min(render_end_index2)..render_end_index2];
 
-        for (index, saved_pattern) in patterns_to_render.iter().enumerate() {
-            let actual_index = index;
+        for (actual_index, global_pattern_index) in patterns_to_render.iter().copied().enumerate() {
+            let saved_pattern = &self.patterns[global_pattern_index];
             let layout_row_index = actual_index / (self.patterns_per_row);
             let layout_col_index = actual_index % (self.patterns_per_row);
             let sub_layout = Layout::default()
@@ -202,31 +1120,285 @@ impl App {
                 .constraints((0..self.patterns_per_row).map(|_| Constraint::Fill(1)))
                 .split(saved_layouts[layout_row_index]);
 
-            self.render_pattern(
+            match self.piece_style {
+                PieceStyle::Full => self.render_pattern(
+                    frame,
+                    sub_layout[layout_col_index],
+                    saved_pattern.clone(),
+                    global_pattern_index,
+                ),
+                PieceStyle::Compact => self.render_pattern_compact(
+                    frame,
+                    sub_layout[layout_col_index],
+                    saved_pattern,
+                    &self.pattern_title(global_pattern_index),
+                    global_pattern_index == self.selected_pattern_index,
+                    self.is_duplicate(global_pattern_index),
+                ),
+            }
+            // self.render_pattern(frame, &prev_layout, &prev_pattern.unwrap().clone());
+        }
+
+        render_footer(self, frame, layout[1]);
+    }
+
+    fn render_confirm(&self, frame: &mut Frame, confirm: &Confirm) {
+        let area = centered_area(frame.area(), 60, 5);
+        let lines = vec![
+            Line::from(confirm.question.as_str()),
+            Line::from(""),
+            Line::from("y/Enter: yes  n/Esc: no"),
+        ];
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Are you sure?")),
+            area,
+        );
+    }
+
+    fn render_help(&self, frame: &mut Frame) {
+        let vim_help = match self.keymap {
+            Keymap::Default => &[],
+            Keymap::Vim => VIM_HELP,
+        };
+        let keys = vim_help.len() + HELP.len();
+        let area = centered_area(frame.area(), 60, keys as u16 + 2);
+        let lines: Vec<Line> = vim_help
+            .iter()
+            .chain(HELP)
+            .map(|(keys, what)| Line::from(format!("{keys:>13}  {what}")))
+            .collect();
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Keys  (h/Esc: close)")),
+            area,
+        );
+    }
+
+    fn render_prompt(&self, frame: &mut Frame, prompt: &Prompt) {
+        let area = centered_area(frame.area(), 70, 5);
+        let hint = match (prompt.kind, self.game.is_some()) {
+            (PromptKind::Rule, true) => "Enter: guess  Up/Down: history  Esc: cancel",
+            (PromptKind::Rule, false) => "Enter: check against the koans  Esc: cancel",
+            (PromptKind::Name, _) => "Enter: save  Esc: cancel",
+            (PromptKind::Jump, _) => "Enter: select  Esc: cancel",
+            (PromptKind::Command, _) => "Enter: run  Tab: complete  Up/Down: history  Esc: cancel",
+        };
+        let block = Block::bordered().title(hint);
+        let inner = block.inner(area);
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+        prompt.input.render(frame, inner, prompt.kind.label(), true);
+    }
+
+    fn render_annotation(&self, frame: &mut Frame, draft: &AnnotationDraft) {
+        let title = match draft.target {
+            Some(index) => format!("Annotate #{index}"),
+            None => "Annotate the editor koan".to_string(),
+        };
+        let area = centered_area(frame.area(), 70, 7);
+        let block = Block::bordered().title(title);
+        let rows = Layout::vertical([Constraint::Length(1); 5]).split(block.inner(area));
+        frame.render_widget(Clear, area);
+        frame.render_widget(block, area);
+
+        for (index, (name, field)) in ["title", "note", "tags"]
+            .iter()
+            .zip(&draft.fields)
+            .enumerate()
+        {
+            field.render(
                 frame,
-                sub_layout[layout_col_index],
-                saved_pattern.clone(),
-                index,
+                rows[index],
+                &format!("{name:>5}: "),
+                index == draft.field,
             );
-            // self.render_pattern(frame, &prev_layout, &prev_pattern.unwrap().clone());
         }
+        frame.render_widget(
+            Paragraph::new("Tab: next field  Enter: save  Esc: cancel"),
+            rows[4],
+        );
+    }
+
+    fn render_log(&self, frame: &mut Frame) {
+        let area = popup_area(frame.area(), 24);
+        let shown = area.height.saturating_sub(4) as usize;
+        let log = self.notifications.log();
+        let end = log.len().saturating_sub(self.log_scroll);
+        let start = end.saturating_sub(shown);
+
+        let mut lines = vec![Line::from("Up/Down: scroll  Esc: close"), Line::from("")];
+        if log.is_empty() {
+            lines.push(Line::from("Nothing happened yet."));
+        }
+        lines.extend(log[start..end].iter().map(|notification| {
+            let age = notification.posted.elapsed().as_secs();
+            let age = match age {
+                0..60 => format!("{age:>3}s"),
+                60..3600 => format!("{:>3}m", age / 60),
+                _ => format!("{:>3}h", age / 3600),
+            };
+            Line::from(vec![
+                Span::raw(format!("{age} ")),
+                Span::styled(
+                    format!("{:<8}", notification.severity),
+                    notification.severity.color(),
+                ),
+                Span::raw(notification.message.clone()),
+            ])
+        }));
+
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Messages")),
+            area,
+        );
+    }
+
+    fn render_advisor(&self, frame: &mut Frame) {
+        let area = popup_area(frame.area(), 20);
+        frame.render_widget(Clear, area);
+        let block = Block::bordered().title("Suggested koan");
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let Some(suggestion) = self.suggestions.get(self.suggestion_index) else {
+            let message = if self.assistant_enabled {
+                "Nothing left to learn, every possible rule agrees."
+            } else {
+                "The rule assistant is disabled for this game."
+            };
+            frame.render_widget(Paragraph::new(message), inner);
+            return;
+        };
+
+        let [text_area, pattern_area] =
+            Layout::vertical([Constraint::Length(4), Constraint::Fill(1)]).areas(inner);
+        let text = vec![
+            Line::from(format!(
+                "valid under {} rules, invalid under {}",
+                suggestion.valid_rules, suggestion.invalid_rules
+            )),
+            Line::from(format!("{:.2} bits expected", suggestion.information)),
+            Line::from("Enter: edit it  n: another  Esc: close"),
+        ];
+        frame.render_widget(Paragraph::new(text), text_area);
+
+        let pattern = PatternParent::new(suggestion.pattern, true);
+        let title = format!("{}/{}", self.suggestion_index + 1, self.suggestions.len());
+        self.render_pattern_compact(frame, pattern_area, &pattern, &title, false, false);
+    }
+
+    /// The rules listed in the rule popup. With the assistant disabled every
+    /// rule is listed, so guessers can still pick one to guess.
+    fn solver_rules(&self) -> Vec<&Rule> {
+        if self.assistant_enabled {
+            self.solver.consistent(&self.patterns).collect()
+        } else {
+            self.solver.rules().iter().collect()
+        }
+    }
+
+    fn render_solver(&self, frame: &mut Frame) {
+        const SHOWN_RULES: usize = 30;
+
+        let rules = self.solver_rules();
+        let header = if self.assistant_enabled {
+            format!(
+                "{} rules fit all {} patterns",
+                rules.len(),
+                self.patterns.len()
+            )
+        } else {
+            "The rule assistant is disabled, showing every rule.".to_string()
+        };
+        let footer = if self.game.is_some() {
+            "Up/Down: select  d: difficulty  Enter: guess  Esc: close"
+        } else {
+            "Up/Down: select  d: difficulty  Esc: close"
+        };
+
+        let first_shown = self.solver_index.saturating_sub(SHOWN_RULES - 1);
+        let mut lines = vec![Line::from(header), Line::from(footer), Line::from("")];
+        lines.extend(
+            rules
+                .iter()
+                .enumerate()
+                .skip(first_shown)
+                .take(SHOWN_RULES)
+                .map(|(index, rule)| {
+                    let line = Line::from(format!("{:>2}  {rule}", rule.complexity()));
+                    if index == self.solver_index {
+                        line.reversed()
+                    } else {
+                        line
+                    }
+                }),
+        );
+
+        let area = popup_area(frame.area(), 24);
+        frame.render_widget(Clear, area);
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title("Possible rules")),
+            area,
+        );
+    }
+
+    /// The color a piece is drawn in, brighter under the cursor. The mono
+    /// theme draws the three colors as shades of gray, light for yellow and
+    /// dark for blue.
+    fn piece_color(&self, color: Color, selected: bool) -> Color {
+        match (self.theme, color, selected) {
+            (Theme::Color, COLOR_BLUE, false) => COLOR_BLUE,
+            (Theme::Color, COLOR_BLUE, true) => COLOR_BLUE_ACTIVE,
+            (Theme::Color, COLOR_RED, false) => COLOR_RED,
+            (Theme::Color, COLOR_RED, true) => COLOR_RED_ACTIVE,
+            (Theme::Color, COLOR_YELLOW, false) => COLOR_YELLOW,
+            (Theme::Color, COLOR_YELLOW, true) => COLOR_YELLOW_ACTIVE,
+            (Theme::Mono, COLOR_BLUE, _) => MONO_BLUE,
+            (Theme::Mono, COLOR_RED, _) => MONO_RED,
+            (Theme::Mono, COLOR_YELLOW, _) => MONO_YELLOW,
+            _ => COLOR_INACTIVE,
+        }
+    }
 
-        self.render_footer(frame, layout[1], "".to_string());
+    /// Whether a cell is drawn highlighted: the cursor, and the cells of a
+    /// block selection.
+    fn marked(&self, pos: (usize, usize)) -> bool {
+        match self.selection {
+            Some(anchor) => in_rectangle(pos, anchor, self.current_pos),
+            None => pos == self.current_pos,
+        }
+    }
 
-        if self.mode == Mode::EDITING {
-            let a = popup_area(frame.area(), 34);
-            frame.render_widget(Clear, a);
+    fn is_duplicate(&self, index: usize) -> bool {
+        self.duplicate
+            .is_some_and(|duplicate| duplicate.index == index)
+    }
 
-            self.render_pattern(frame, a, self.data_big.clone(), 0);
+    fn pattern_title(&self, index: usize) -> String {
+        let mut title = format!("#{index}");
+        if let Some(pattern) = self.patterns.get(index) {
+            let annotation = &pattern.annotation;
+            if !annotation.title.is_empty() {
+                title.push_str(&format!(" {}", annotation.title));
+            }
+            if !annotation.tags.is_empty() {
+                title.push_str(&format!(" [{}]", annotation.tags.join(" ")));
+            }
+        }
+        if self.is_duplicate(index) {
+            title.push_str(DUPLICATE_MARK);
         }
+        title
     }
 
     fn render_pattern(
-        &mut self,
+        &self,
         frame: &mut Frame<'_>,
         edit_layout: Rect,
         pattern_parent: PatternParent,
-        pattern_index: usize,
+        global_pattern_index: usize,
     ) {
         let pattern = pattern_parent.data;
         let subbo_layout = Layout::default()
@@ -254,7 +1426,6 @@ impl App {
             PATTERN_BORDER_INVALID
         };
 
-        let global_pattern_index = self.render_start_index + pattern_index;
         let is_selected = global_pattern_index == self.selected_pattern_index;
 
         if is_selected {
@@ -264,10 +1435,13 @@ impl App {
                 _ => (),
             }
         };
+        if self.is_duplicate(global_pattern_index) {
+            border_color = PATTERN_BORDER_DUPLICATE;
+        }
 
         let block_widget = Block::bordered()
             .border_style(border_color)
-            .title(format!("#{global_pattern_index}"));
+            .title(self.pattern_title(global_pattern_index));
 
         frame.render_widget(block_widget, border_layout[0]);
 
@@ -278,32 +1452,19 @@ impl App {
                 .split(*layout);
 
             for (col_index, row_layout) in row_layouts.iter().enumerate() {
-                let pyramid = pattern
-                    .iter()
-                    .find(|d| d.pos.0 == col_index && d.pos.1 == row_index);
-                let selected_symbol = self.current_pos == (col_index, row_index) && is_selected;
+                let pyramid = pattern.pyramid_at((col_index, row_index));
+                let selected_symbol = self.marked((col_index, row_index)) && is_selected;
                 match pyramid {
                     Some(pyramid) => {
-                        let mut pyramid_color = match pyramid.pos {
-                            _ => pyramid.color,
-                        };
-
                         // let selected_symbol = pyramid.pos == self.current_pos;
-
-                        if selected_symbol {
-                            pyramid_color = match pyramid.color {
-                                COLOR_BLUE => COLOR_BLUE_ACTIVE,
-                                COLOR_RED => COLOR_RED_ACTIVE,
-                                COLOR_YELLOW => COLOR_YELLOW_ACTIVE,
-                                _ => COLOR_INACTIVE,
-                            }
-                        }
+                        let pyramid_color = self.piece_color(pyramid.color, selected_symbol);
 
                         if pyramid.pyramid_type == PyramidType::Angled {
                             render_top_down_pyramid_angled(
                                 frame,
                                 *row_layout,
                                 pyramid.size,
+                                pyramid.turned,
                                 pyramid_color,
                                 selected_symbol,
                             )
@@ -324,11 +1485,73 @@ impl App {
         }
     }
 
+    fn render_pattern_compact(
+        &self,
+        frame: &mut Frame<'_>,
+        area: Rect,
+        pattern_parent: &PatternParent,
+        title: &str,
+        is_selected: bool,
+        is_duplicate: bool,
+    ) {
+        let border_color = match (pattern_parent.valid, is_selected) {
+            (true, true) => PATTERN_BORDER_VALID_ACTIVE,
+            (true, false) => PATTERN_BORDER_VALID,
+            (false, true) => PATTERN_BORDER_INVALID_ACTIVE,
+            (false, false) => PATTERN_BORDER_INVALID,
+        };
+        let border_color = match is_duplicate {
+            true => PATTERN_BORDER_DUPLICATE,
+            false => border_color,
+        };
+
+        let border_area = Rect {
+            width: area.width.min(COMPACT_CELL_WIDTH * WIDTH as u16 + 2),
+            height: area.height.min(COMPACT_CELL_HEIGHT * HEIGHT as u16 + 2),
+            ..area
+        };
+        let block_widget = Block::bordered().border_style(border_color).title(title);
+        let inner = block_widget.inner(border_area);
+        frame.render_widget(block_widget, border_area);
+
+        let rows = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints((0..HEIGHT).map(|_| Constraint::Length(COMPACT_CELL_HEIGHT)))
+            .split(inner);
+
+        for (row_index, row) in rows.iter().enumerate() {
+            let cells = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints((0..WIDTH).map(|_| Constraint::Length(COMPACT_CELL_WIDTH)))
+                .split(*row);
+
+            for (col_index, cell) in cells.iter().enumerate() {
+                let pyramid = pattern_parent.data.pyramid_at((col_index, row_index));
+                let selected_symbol = self.marked((col_index, row_index)) && is_selected;
+
+                let color = match pyramid {
+                    Some(pyramid) => self.piece_color(pyramid.color, selected_symbol),
+                    None => Color::White,
+                };
+
+                render_compact_pyramid(
+                    frame,
+                    *cell,
+                    pyramid.map(|p| p.piece()),
+                    color,
+                    self.glyphs,
+                    selected_symbol,
+                );
+            }
+        }
+    }
+
     fn handle_crossterm_events(&mut self) -> Result<()> {
         match event::read()? {
             // it's important to check KeyEventKind::Press to avoid handling key release events
             Event::Key(key) if key.kind == KeyEventKind::Press => on_key_event(self, key),
-            Event::Mouse(_) => {}
+            Event::Paste(text) => on_paste(self, &text),
+            Event::Mouse(mouse) => on_mouse_event(self, mouse),
             Event::Resize(_, _) => {}
             _ => {}
         }
@@ -340,3 +1563,25 @@ impl App {
         self.running = false;
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn leaves_positions_off_the_grid_alone() {
+        let piece = App::new().brush;
+        let mut pattern = Pattern::default();
+        for pos in [(WIDTH, 0), (0, HEIGHT), (usize::MAX, usize::MAX)] {
+            assert_eq!(pattern.set(pos, piece), None);
+            assert_eq!(pattern.get(pos), None);
+            assert_eq!(pattern.update(pos, |piece| piece.turned = true), None);
+            assert_eq!(pattern.clear(pos), None);
+        }
+        assert!(pattern.is_empty());
+
+        assert_eq!(pattern.set((2, 2), piece), None);
+        assert_eq!(pattern.set((2, 2), piece), Some(piece));
+        assert_eq!(pattern.len(), 1);
+    }
+}
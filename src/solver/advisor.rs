@@ -0,0 +1,113 @@
+use crate::{
+    COLOR_BLUE, COLOR_RED, COLOR_YELLOW, Datos, HEIGHT, Pattern, PatternParent, PyramidType,
+    SymbolSize, WIDTH, helpers::rng::Rng,
+};
+
+use super::rule::Rule;
+
+/// How many random and mutated patterns are scored per suggestion round.
+const CANDIDATES: usize = 400;
+/// How many of the best candidates are kept for "give me another".
+const KEPT: usize = 10;
+
+/// A pattern worth building next, and how it splits the possible rules.
+#[derive(Debug, Clone)]
+pub struct Suggestion {
+    pub pattern: Pattern,
+    /// Possible rules under which the pattern would be valid.
+    pub valid_rules: usize,
+    /// Possible rules under which the pattern would be invalid.
+    pub invalid_rules: usize,
+    /// Expected information of the master's answer, in bits.
+    pub information: f64,
+}
+
+pub fn random_piece(rng: &mut Rng, pos: (usize, usize)) -> Datos {
+    Datos {
+        pos,
+        pyramid_type: *rng.pick(&[PyramidType::Straight, PyramidType::Angled]),
+        color: *rng.pick(&[COLOR_RED, COLOR_BLUE, COLOR_YELLOW]),
+        size: *rng.pick(&[SymbolSize::SMALL, SymbolSize::MEDIUM, SymbolSize::LARGE]),
+        turned: false,
+    }
+}
+
+/// A pattern with up to six random pieces.
+pub fn random_pattern(rng: &mut Rng) -> Pattern {
+    let mut cells: Vec<(usize, usize)> = (0..HEIGHT)
+        .flat_map(|row| (0..WIDTH).map(move |col| (col, row)))
+        .collect();
+    let pieces = rng.below(7);
+
+    let mut pattern = Pattern::default();
+    for _ in 0..pieces {
+        let pos = cells.swap_remove(rng.below(cells.len()));
+        pattern.place(random_piece(rng, pos));
+    }
+    pattern
+}
+
+/// Changes one cell of the pattern: adds, removes or replaces a piece.
+pub fn mutate(pattern: &Pattern, rng: &mut Rng) -> Pattern {
+    let mut pattern = *pattern;
+    let pos = (rng.below(WIDTH), rng.below(HEIGHT));
+    if pattern.get(pos).is_some() && rng.below(3) == 0 {
+        pattern.clear(pos);
+    } else {
+        pattern.place(random_piece(rng, pos));
+    }
+    pattern
+}
+
+/// Scores random patterns and small changes to the judged ones, and returns
+/// the best few, most informative first.
+///
+/// Simple rules are more likely to be the master's, so each rule counts with
+/// weight `2^-complexity` when measuring how evenly a pattern splits them.
+pub fn suggest(rules: &[&Rule], patterns: &[PatternParent], rng: &mut Rng) -> Vec<Suggestion> {
+    if rules.len() < 2 {
+        return vec![];
+    }
+
+    let mut suggestions: Vec<Suggestion> = (0..CANDIDATES)
+        .map(|index| {
+            if patterns.is_empty() || index % 2 == 0 {
+                random_pattern(rng)
+            } else {
+                let base = &rng.pick(patterns).data;
+                mutate(base, rng)
+            }
+        })
+        .map(|pattern| score(pattern, rules))
+        .collect();
+
+    suggestions.sort_by(|a, b| b.information.total_cmp(&a.information));
+    suggestions.truncate(KEPT);
+    suggestions
+}
+
+fn score(pattern: Pattern, rules: &[&Rule]) -> Suggestion {
+    let (mut valid_rules, mut valid_weight, mut total_weight) = (0, 0.0, 0.0);
+    for rule in rules {
+        let weight = 0.5_f64.powi(rule.complexity() as i32);
+        total_weight += weight;
+        if rule.holds(&pattern) {
+            valid_rules += 1;
+            valid_weight += weight;
+        }
+    }
+
+    let p = valid_weight / total_weight;
+    let information = [p, 1.0 - p]
+        .iter()
+        .filter(|p| **p > 0.0)
+        .map(|p| -p * p.log2())
+        .sum();
+
+    Suggestion {
+        invalid_rules: rules.len() - valid_rules,
+        valid_rules,
+        pattern,
+        information,
+    }
+}
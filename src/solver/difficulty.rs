@@ -0,0 +1,182 @@
+use std::fmt;
+
+use crate::{
+    HEIGHT, Pattern, WIDTH,
+    game::{guesser::Strategy, simulate},
+    helpers::rng::Rng,
+};
+
+use super::{
+    MAX_COMPLEXITY,
+    rule::Rule,
+    space::{PIECES, piece},
+};
+
+const ACCEPTANCE_SAMPLES: usize = 10_000;
+const SIMULATIONS: usize = 3;
+const MAX_TURNS: usize = 40;
+
+/// How much the bot turns, the complexity and an even split of the koans
+/// count towards the rating. The bots know every rule the solver does, so
+/// they crack all of those in a similar number of turns, and the complexity
+/// tells them apart.
+const TURNS_WEIGHT: f64 = 0.5;
+const COMPLEXITY_WEIGHT: f64 = 0.35;
+const BALANCE_WEIGHT: f64 = 0.15;
+
+/// The lowest score of each rating above trivial.
+const RATING_SCORES: [(f64, Rating); 4] = [
+    (0.7, Rating::Brutal),
+    (0.5, Rating::Hard),
+    (0.35, Rating::Medium),
+    (0.2, Rating::Easy),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Rating {
+    Trivial,
+    Easy,
+    Medium,
+    Hard,
+    Brutal,
+}
+
+impl fmt::Display for Rating {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Rating::Trivial => "trivial",
+            Rating::Easy => "easy",
+            Rating::Medium => "medium",
+            Rating::Hard => "hard",
+            Rating::Brutal => "brutal",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How hard a rule is likely to be to crack.
+#[derive(Debug, Clone)]
+pub struct Difficulty {
+    /// Share of all 3x3 patterns the rule calls valid, estimated by sampling.
+    pub acceptance: f64,
+    pub complexity: usize,
+    /// Average guesser turns the greedy bot needed, `None` when it never
+    /// found the rule within the turn limit.
+    pub turns: Option<f64>,
+    /// From the turns, the complexity and the acceptance together, see
+    /// `score`.
+    pub rating: Rating,
+    pub warnings: Vec<&'static str>,
+}
+
+impl fmt::Display for Difficulty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let turns = match self.turns {
+            Some(turns) => format!("bots need ~{turns:.0} turns"),
+            None => format!("bots did not crack it in {MAX_TURNS} turns"),
+        };
+        write!(
+            f,
+            "{}: accepts {:.1}% of patterns, complexity {}, {turns}",
+            self.rating,
+            self.acceptance * 100.0,
+            self.complexity
+        )?;
+        for warning in &self.warnings {
+            write!(f, "; {warning}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A pattern drawn uniformly from every 3x3 configuration: each cell is empty
+/// or holds any of the 18 pieces with equal chance.
+pub fn uniform_pattern(rng: &mut Rng) -> Pattern {
+    let mut pattern = Pattern::default();
+    for row in 0..HEIGHT {
+        for col in 0..WIDTH {
+            let state = rng.below(PIECES + 1);
+            if state < PIECES {
+                pattern.set((col, row), piece(state));
+            }
+        }
+    }
+    pattern
+}
+
+pub fn estimate(rule: &Rule, rng: &mut Rng) -> Difficulty {
+    let accepted = (0..ACCEPTANCE_SAMPLES)
+        .filter(|_| rule.holds(&uniform_pattern(rng)))
+        .count();
+    let acceptance = accepted as f64 / ACCEPTANCE_SAMPLES as f64;
+    let complexity = rule.complexity();
+
+    let runs: Vec<Option<usize>> = (0..SIMULATIONS)
+        .map(|_| simulate(rule.clone(), Strategy::Greedy, rng, MAX_TURNS))
+        .collect();
+    let turns = match runs.iter().all(Option::is_some) {
+        true => Some(runs.iter().flatten().sum::<usize>() as f64 / SIMULATIONS as f64),
+        false => None,
+    };
+
+    let score = score(acceptance, complexity, turns);
+    let rating = RATING_SCORES
+        .iter()
+        .find(|(lowest, _)| score >= *lowest)
+        .map_or(Rating::Trivial, |(_, rating)| *rating);
+
+    let mut warnings = vec![];
+    if acceptance < 0.02 {
+        warnings.push("accepts almost nothing, valid koans will be rare");
+    }
+    if acceptance > 0.98 {
+        warnings.push("accepts almost everything, invalid koans will be rare");
+    }
+    if rating == Rating::Trivial {
+        warnings.push("trivial, expect a short game");
+    }
+
+    Difficulty {
+        acceptance,
+        complexity,
+        turns,
+        rating,
+        warnings,
+    }
+}
+
+/// How hard a rule is, from 0 to 1. Rules the bots did not crack count as
+/// taking every turn. A rule that gives almost every koan the same verdict is
+/// nearly guessed by saying so, so an even split counts as harder.
+fn score(acceptance: f64, complexity: usize, turns: Option<f64>) -> f64 {
+    let turns = turns.map_or(1.0, |turns| (turns / MAX_TURNS as f64).min(1.0));
+    let complexity = (complexity as f64 / MAX_COMPLEXITY as f64).min(1.0);
+    let balance = 2.0 * acceptance.min(1.0 - acceptance);
+    TURNS_WEIGHT * turns + COMPLEXITY_WEIGHT * complexity + BALANCE_WEIGHT * balance
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rating(rule: &str) -> Rating {
+        estimate(&rule.parse().unwrap(), &mut Rng::new(0)).rating
+    }
+
+    #[test]
+    fn rates_a_hard_rule_above_a_trivial_one() {
+        let trivial = rating("there is a piece");
+        let hard = rating("a large red piece touches a small blue piece");
+        assert_eq!(trivial, Rating::Trivial);
+        assert!(hard > trivial);
+        assert!(hard >= Rating::Hard);
+    }
+
+    #[test]
+    fn weighs_more_than_the_turns() {
+        let turns = Some(8.0);
+        assert!(score(0.5, 4, turns) > score(0.5, 1, turns));
+        assert!(score(0.5, 3, turns) > score(0.01, 3, turns));
+        assert!(score(0.5, 3, None) > score(0.5, 3, turns));
+    }
+}
@@ -0,0 +1,95 @@
+pub mod advisor;
+pub mod difficulty;
+pub mod rule;
+pub mod space;
+
+use crate::{Pattern, PatternParent, helpers::rng::Rng};
+use advisor::{mutate, random_pattern};
+use rule::{Rule, enumerate_rules};
+use space::{canonical, encode};
+
+/// Rules up to this complexity are considered, which keeps the list in the
+/// low thousands so filtering it is cheap enough to redo on every koan.
+pub const MAX_COMPLEXITY: usize = 5;
+
+/// Lists the rules that are still possible given the judged patterns.
+#[derive(Debug, Clone)]
+pub struct Solver {
+    rules: Vec<Rule>,
+}
+
+impl Default for Solver {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Solver {
+    pub fn new() -> Self {
+        Self {
+            rules: enumerate_rules(MAX_COMPLEXITY),
+        }
+    }
+
+    pub fn rules(&self) -> &[Rule] {
+        &self.rules
+    }
+
+    /// Rules that agree with the verdict of every pattern, simplest first.
+    pub fn consistent<'a>(
+        &'a self,
+        patterns: &'a [PatternParent],
+    ) -> impl Iterator<Item = &'a Rule> {
+        self.rules.iter().filter(move |rule| {
+            patterns
+                .iter()
+                .all(|pattern| rule.holds(&pattern.data) == pattern.valid)
+        })
+    }
+}
+
+/// How many patterns are tried before two rules are taken to be the same.
+const COUNTEREXAMPLE_TRIES: usize = 20_000;
+
+/// Looks for a pattern on which the two rules disagree, trying random patterns
+/// and small changes to the `seeds`. `None` means the rules are very likely
+/// equivalent.
+pub fn find_counterexample(
+    a: &Rule,
+    b: &Rule,
+    seeds: &[PatternParent],
+    rng: &mut Rng,
+) -> Option<Pattern> {
+    (0..COUNTEREXAMPLE_TRIES)
+        .map(|index| {
+            if seeds.is_empty() || index % 2 == 0 {
+                random_pattern(rng)
+            } else {
+                let seed = &rng.pick(seeds).data;
+                mutate(seed, rng)
+            }
+        })
+        .find(|pattern| a.holds(pattern) != b.holds(pattern))
+}
+
+/// Finds a saved pattern with the same pieces in the same places. With
+/// `symmetric` a rotation or reflection counts too, and the flag returned
+/// tells whether only such a one matched.
+pub fn find_duplicate(
+    pattern: &Pattern,
+    saved: &[PatternParent],
+    symmetric: bool,
+) -> Option<(usize, bool)> {
+    let code = encode(pattern);
+    if let Some(index) = saved.iter().position(|p| encode(&p.data) == code) {
+        return Some((index, false));
+    }
+    if !symmetric {
+        return None;
+    }
+    let code = canonical(pattern);
+    saved
+        .iter()
+        .position(|p| canonical(&p.data) == code)
+        .map(|index| (index, true))
+}
@@ -0,0 +1,565 @@
+use std::fmt;
+
+use ratatui::style::Color;
+
+use crate::{
+    COLOR_BLUE, COLOR_RED, COLOR_YELLOW, Datos, HEIGHT, Pattern, PyramidType, SymbolSize, WIDTH,
+};
+
+/// A single property a piece can have.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attr {
+    Color(Color),
+    Size(SymbolSize),
+    Type(PyramidType),
+}
+
+/// Selects the pieces a rule talks about, e.g. "large red pieces".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Filter {
+    Any,
+    Is(Attr),
+    And(Attr, Attr),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cmp {
+    AtLeast,
+    AtMost,
+    Exactly,
+}
+
+/// Part of the grid a piece can be in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Region {
+    Row(usize),
+    Column(usize),
+    Center,
+    Corner,
+    Edge,
+}
+
+/// A rule the master could have picked.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    /// "at least 2 red pieces", "no large pieces"
+    Count(Filter, Cmp, usize),
+    /// "every red piece is large"
+    All(Filter, Attr),
+    /// "a red piece touches a blue piece", sharing an edge
+    Adjacent(Filter, Filter),
+    /// "a red piece is in the top row"
+    InRegion(Filter, Region),
+    /// "more red pieces than blue pieces"
+    MoreThan(Filter, Filter),
+    Not(Box<Rule>),
+}
+
+impl Attr {
+    pub const ALL: [Attr; 8] = [
+        Attr::Color(COLOR_RED),
+        Attr::Color(COLOR_BLUE),
+        Attr::Color(COLOR_YELLOW),
+        Attr::Size(SymbolSize::SMALL),
+        Attr::Size(SymbolSize::MEDIUM),
+        Attr::Size(SymbolSize::LARGE),
+        Attr::Type(PyramidType::Straight),
+        Attr::Type(PyramidType::Angled),
+    ];
+
+    pub fn matches(&self, pyramid: &Datos) -> bool {
+        match *self {
+            Attr::Color(color) => pyramid.color == color,
+            Attr::Size(size) => pyramid.size == size,
+            Attr::Type(pyramid_type) => pyramid.pyramid_type == pyramid_type,
+        }
+    }
+
+    /// Whether both attributes describe the same property, e.g. two colors.
+    pub fn same_kind(&self, other: &Attr) -> bool {
+        matches!(
+            (self, other),
+            (Attr::Color(_), Attr::Color(_))
+                | (Attr::Size(_), Attr::Size(_))
+                | (Attr::Type(_), Attr::Type(_))
+        )
+    }
+}
+
+impl Filter {
+    pub fn matches(&self, pyramid: &Datos) -> bool {
+        match self {
+            Filter::Any => true,
+            Filter::Is(attr) => attr.matches(pyramid),
+            Filter::And(a, b) => a.matches(pyramid) && b.matches(pyramid),
+        }
+    }
+
+    pub fn count(&self, pattern: &Pattern) -> usize {
+        pattern.iter().filter(|d| self.matches(d)).count()
+    }
+
+    fn complexity(&self) -> usize {
+        match self {
+            Filter::Any => 0,
+            Filter::Is(_) => 1,
+            Filter::And(_, _) => 2,
+        }
+    }
+
+    fn mentions_kind(&self, attr: &Attr) -> bool {
+        match self {
+            Filter::Any => false,
+            Filter::Is(a) => a.same_kind(attr),
+            Filter::And(a, b) => a.same_kind(attr) || b.same_kind(attr),
+        }
+    }
+}
+
+impl Region {
+    pub const ALL: [Region; 9] = [
+        Region::Row(0),
+        Region::Row(1),
+        Region::Row(2),
+        Region::Column(0),
+        Region::Column(1),
+        Region::Column(2),
+        Region::Center,
+        Region::Corner,
+        Region::Edge,
+    ];
+
+    pub fn contains(&self, (col, row): (usize, usize)) -> bool {
+        let border_col = col == 0 || col == WIDTH - 1;
+        let border_row = row == 0 || row == HEIGHT - 1;
+        match *self {
+            Region::Row(r) => row == r,
+            Region::Column(c) => col == c,
+            Region::Center => col == WIDTH / 2 && row == HEIGHT / 2,
+            Region::Corner => border_col && border_row,
+            Region::Edge => border_col != border_row,
+        }
+    }
+}
+
+fn touches(a: (usize, usize), b: (usize, usize)) -> bool {
+    a.0.abs_diff(b.0) + a.1.abs_diff(b.1) == 1
+}
+
+impl Rule {
+    /// Whether the pattern follows the rule, i.e. the master would call it valid.
+    pub fn holds(&self, pattern: &Pattern) -> bool {
+        match self {
+            Rule::Count(filter, cmp, n) => {
+                let count = filter.count(pattern);
+                match cmp {
+                    Cmp::AtLeast => count >= *n,
+                    Cmp::AtMost => count <= *n,
+                    Cmp::Exactly => count == *n,
+                }
+            }
+            Rule::All(filter, attr) => pattern
+                .iter()
+                .filter(|d| filter.matches(d))
+                .all(|d| attr.matches(&d)),
+            Rule::Adjacent(a, b) => pattern.iter().filter(|d| a.matches(d)).any(|first| {
+                pattern
+                    .iter()
+                    .any(|second| b.matches(&second) && touches(first.pos, second.pos))
+            }),
+            Rule::InRegion(filter, region) => pattern
+                .iter()
+                .any(|d| filter.matches(&d) && region.contains(d.pos)),
+            Rule::MoreThan(a, b) => a.count(pattern) > b.count(pattern),
+            Rule::Not(rule) => !rule.holds(pattern),
+        }
+    }
+
+    /// The filters the verdict depends on. Pieces that match the same ones
+    /// are all the same to the rule.
+    pub fn filters(&self) -> Vec<Filter> {
+        match self {
+            Rule::Count(filter, _, _) | Rule::InRegion(filter, _) => vec![*filter],
+            Rule::All(filter, attr) => vec![*filter, Filter::Is(*attr)],
+            Rule::Adjacent(a, b) | Rule::MoreThan(a, b) => vec![*a, *b],
+            Rule::Not(rule) => rule.filters(),
+        }
+    }
+
+    /// Whether rotating or mirroring a pattern can never change the verdict,
+    /// which holds unless the rule names a particular row or column.
+    pub fn ignores_position(&self) -> bool {
+        match self {
+            Rule::InRegion(_, Region::Row(_) | Region::Column(_)) => false,
+            Rule::Not(rule) => rule.ignores_position(),
+            _ => true,
+        }
+    }
+
+    /// Rough measure of how hard the rule is to state. Guessers should try
+    /// simple rules first.
+    pub fn complexity(&self) -> usize {
+        match self {
+            Rule::Count(filter, Cmp::AtLeast, 1) => 1 + filter.complexity(),
+            Rule::Count(filter, _, _) => 2 + filter.complexity(),
+            Rule::All(filter, _) => 2 + filter.complexity(),
+            Rule::Adjacent(a, b) => 2 + a.complexity() + b.complexity(),
+            Rule::InRegion(filter, _) => 2 + filter.complexity(),
+            Rule::MoreThan(a, b) => 2 + a.complexity() + b.complexity(),
+            Rule::Not(rule) => 1 + rule.complexity(),
+        }
+    }
+}
+
+/// Every filter: any piece, one attribute, or two attributes of different kinds.
+pub fn all_filters() -> Vec<Filter> {
+    let mut filters = vec![Filter::Any];
+    filters.extend(Attr::ALL.iter().map(|attr| Filter::Is(*attr)));
+    for (index, a) in Attr::ALL.iter().enumerate() {
+        for b in &Attr::ALL[index + 1..] {
+            if !a.same_kind(b) {
+                filters.push(Filter::And(*a, *b));
+            }
+        }
+    }
+    filters
+}
+
+/// Enumerates every rule up to `max_complexity`, simplest first.
+pub fn enumerate_rules(max_complexity: usize) -> Vec<Rule> {
+    let filters = all_filters();
+    let mut rules = vec![];
+
+    for filter in &filters {
+        for n in 1..=4 {
+            rules.push(Rule::Count(*filter, Cmp::AtLeast, n));
+        }
+        for n in 0..=3 {
+            rules.push(Rule::Count(*filter, Cmp::AtMost, n));
+        }
+        for n in 1..=3 {
+            rules.push(Rule::Count(*filter, Cmp::Exactly, n));
+        }
+        for attr in &Attr::ALL {
+            if !filter.mentions_kind(attr) {
+                rules.push(Rule::All(*filter, *attr));
+            }
+        }
+        for region in &Region::ALL {
+            let rule = Rule::InRegion(*filter, *region);
+            rules.push(Rule::Not(Box::new(rule.clone())));
+            rules.push(rule);
+        }
+    }
+
+    for (index, a) in filters.iter().enumerate() {
+        for b in &filters[index..] {
+            let rule = Rule::Adjacent(*a, *b);
+            rules.push(Rule::Not(Box::new(rule.clone())));
+            rules.push(rule);
+        }
+    }
+
+    for a in &Attr::ALL {
+        for b in &Attr::ALL {
+            if a != b && a.same_kind(b) {
+                rules.push(Rule::MoreThan(Filter::Is(*a), Filter::Is(*b)));
+            }
+        }
+    }
+
+    rules.retain(|rule| rule.complexity() <= max_complexity);
+    rules.sort_by_key(Rule::complexity);
+    rules
+}
+
+fn attr_name(attr: &Attr) -> &'static str {
+    match attr {
+        Attr::Color(COLOR_RED) => "red",
+        Attr::Color(COLOR_BLUE) => "blue",
+        Attr::Color(COLOR_YELLOW) => "yellow",
+        Attr::Color(_) => "colored",
+        Attr::Size(SymbolSize::SMALL) => "small",
+        Attr::Size(SymbolSize::MEDIUM) => "medium",
+        Attr::Size(SymbolSize::LARGE) => "large",
+        Attr::Type(PyramidType::Straight) => "straight",
+        Attr::Type(PyramidType::Angled) => "angled",
+    }
+}
+
+impl fmt::Display for Attr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(attr_name(self))
+    }
+}
+
+impl fmt::Display for Filter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Sizes read best first, "large red", so order the pair by kind.
+        let order = |attr: &Attr| match attr {
+            Attr::Size(_) => 0,
+            Attr::Color(_) => 1,
+            Attr::Type(_) => 2,
+        };
+        match self {
+            Filter::Any => write!(f, "piece"),
+            Filter::Is(attr) => write!(f, "{attr} piece"),
+            Filter::And(a, b) if order(a) <= order(b) => write!(f, "{a} {b} piece"),
+            Filter::And(a, b) => write!(f, "{b} {a} piece"),
+        }
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let row_names = ["top", "middle", "bottom"];
+        let column_names = ["left", "middle", "right"];
+        match *self {
+            Region::Row(row) => write!(f, "the {} row", row_names[row]),
+            Region::Column(col) => write!(f, "the {} column", column_names[col]),
+            Region::Center => write!(f, "the center"),
+            Region::Corner => write!(f, "a corner"),
+            Region::Edge => write!(f, "an edge"),
+        }
+    }
+}
+
+impl fmt::Display for Rule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Rule::Count(filter, Cmp::AtLeast, 1) => write!(f, "there is a {filter}"),
+            Rule::Count(filter, Cmp::AtMost, 0) => write!(f, "there is no {filter}"),
+            Rule::Count(filter, cmp, n) => {
+                let cmp = match cmp {
+                    Cmp::AtLeast => "at least",
+                    Cmp::AtMost => "at most",
+                    Cmp::Exactly => "exactly",
+                };
+                let plural = if *n == 1 { "" } else { "s" };
+                write!(f, "{cmp} {n} {filter}{plural}")
+            }
+            Rule::All(filter, attr) => write!(f, "every {filter} is {attr}"),
+            Rule::Adjacent(a, b) => write!(f, "a {a} touches a {b}"),
+            Rule::InRegion(filter, region) => write!(f, "a {filter} is in {region}"),
+            Rule::MoreThan(a, b) => write!(f, "more {a}s than {b}s"),
+            Rule::Not(rule) => match rule.as_ref() {
+                Rule::Adjacent(a, b) => write!(f, "no {a} touches a {b}"),
+                Rule::InRegion(filter, region) => write!(f, "no {filter} is in {region}"),
+                rule => write!(f, "not ({rule})"),
+            },
+        }
+    }
+}
+
+/// Why a typed rule could not be read.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RuleParseError {
+    pub expected: &'static str,
+    /// The word that was found instead, empty at the end of the rule.
+    pub found: String,
+}
+
+impl fmt::Display for RuleParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.found.is_empty() {
+            write!(f, "expected {} at the end of the rule", self.expected)
+        } else {
+            write!(f, "expected {} but found '{}'", self.expected, self.found)
+        }
+    }
+}
+
+impl std::error::Error for RuleParseError {}
+
+/// Reads rules written the way they are displayed, e.g. "every red piece is
+/// large" or "no small piece touches a blue piece".
+struct RuleParser<'a> {
+    words: Vec<&'a str>,
+    index: usize,
+}
+
+impl<'a> RuleParser<'a> {
+    fn peek(&self) -> &'a str {
+        self.words.get(self.index).copied().unwrap_or("")
+    }
+
+    fn next(&mut self) -> &'a str {
+        let word = self.peek();
+        self.index += 1;
+        word
+    }
+
+    fn error(&self, expected: &'static str) -> RuleParseError {
+        RuleParseError {
+            expected,
+            found: self.peek().to_string(),
+        }
+    }
+
+    fn eat(&mut self, word: &str) -> bool {
+        let found = self.peek() == word;
+        if found {
+            self.index += 1;
+        }
+        found
+    }
+
+    fn expect(&mut self, word: &'static str) -> Result<(), RuleParseError> {
+        match self.eat(word) {
+            true => Ok(()),
+            false => Err(self.error(word)),
+        }
+    }
+
+    fn attr(&mut self) -> Option<Attr> {
+        let attr = Attr::ALL
+            .into_iter()
+            .find(|attr| attr_name(attr) == self.peek())?;
+        self.index += 1;
+        Some(attr)
+    }
+
+    fn filter(&mut self) -> Result<Filter, RuleParseError> {
+        let filter = match (self.attr(), self.attr()) {
+            (None, _) => Filter::Any,
+            (Some(attr), None) => Filter::Is(attr),
+            (Some(a), Some(b)) if !a.same_kind(&b) => {
+                // keep the order `all_filters` uses, so equal filters compare equal
+                let index = |attr: Attr| Attr::ALL.iter().position(|a| *a == attr);
+                match index(a) < index(b) {
+                    true => Filter::And(a, b),
+                    false => Filter::And(b, a),
+                }
+            }
+            (Some(_), Some(_)) => return Err(self.error("a piece")),
+        };
+        match self.next() {
+            "piece" | "pieces" => Ok(filter),
+            _ => {
+                self.index -= 1;
+                Err(self.error("piece"))
+            }
+        }
+    }
+
+    fn region(&mut self) -> Result<Region, RuleParseError> {
+        let region = match (self.next(), self.next(), self.next()) {
+            ("the", "center", _) => {
+                self.index -= 1;
+                Region::Center
+            }
+            ("a", "corner", _) => {
+                self.index -= 1;
+                Region::Corner
+            }
+            ("an", "edge", _) => {
+                self.index -= 1;
+                Region::Edge
+            }
+            ("the", "top", "row") => Region::Row(0),
+            ("the", "middle", "row") => Region::Row(1),
+            ("the", "bottom", "row") => Region::Row(2),
+            ("the", "left", "column") => Region::Column(0),
+            ("the", "middle", "column") => Region::Column(1),
+            ("the", "right", "column") => Region::Column(2),
+            _ => {
+                self.index -= 3;
+                return Err(self.error("a row, a column, the center, a corner or an edge"));
+            }
+        };
+        Ok(region)
+    }
+
+    /// `a <filter> touches a <filter>` or `a <filter> is in <region>`, after
+    /// the first word.
+    fn placement(&mut self) -> Result<Rule, RuleParseError> {
+        let filter = self.filter()?;
+        if self.eat("touches") {
+            if !self.eat("a") && !self.eat("an") {
+                return Err(self.error("a"));
+            }
+            return Ok(Rule::Adjacent(filter, self.filter()?));
+        }
+        self.expect("is")?;
+        self.expect("in")?;
+        Ok(Rule::InRegion(filter, self.region()?))
+    }
+
+    fn rule(&mut self) -> Result<Rule, RuleParseError> {
+        let rule = match self.next() {
+            "there" => {
+                self.expect("is")?;
+                if self.eat("no") {
+                    Rule::Count(self.filter()?, Cmp::AtMost, 0)
+                } else if self.eat("a") || self.eat("an") {
+                    Rule::Count(self.filter()?, Cmp::AtLeast, 1)
+                } else {
+                    return Err(self.error("a or no"));
+                }
+            }
+            "at" => {
+                let cmp = match self.next() {
+                    "least" => Cmp::AtLeast,
+                    "most" => Cmp::AtMost,
+                    _ => {
+                        self.index -= 1;
+                        return Err(self.error("least or most"));
+                    }
+                };
+                let n = self.number()?;
+                Rule::Count(self.filter()?, cmp, n)
+            }
+            "exactly" => {
+                let n = self.number()?;
+                Rule::Count(self.filter()?, Cmp::Exactly, n)
+            }
+            "every" => {
+                let filter = self.filter()?;
+                self.expect("is")?;
+                let attr = self
+                    .attr()
+                    .ok_or_else(|| self.error("a color, size or type"))?;
+                Rule::All(filter, attr)
+            }
+            "a" | "an" => self.placement()?,
+            "no" => Rule::Not(Box::new(self.placement()?)),
+            "more" => {
+                let a = self.filter()?;
+                self.expect("than")?;
+                Rule::MoreThan(a, self.filter()?)
+            }
+            "not" => Rule::Not(Box::new(self.rule()?)),
+            _ => {
+                self.index -= 1;
+                return Err(self.error("the start of a rule"));
+            }
+        };
+        Ok(rule)
+    }
+
+    fn number(&mut self) -> Result<usize, RuleParseError> {
+        let n = self.peek().parse().map_err(|_| self.error("a number"))?;
+        self.index += 1;
+        Ok(n)
+    }
+}
+
+impl std::str::FromStr for Rule {
+    type Err = RuleParseError;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let text = text.trim().trim_end_matches('.').to_lowercase();
+        // "not (...)" is displayed with parentheses, which carry no meaning here
+        let text = text.replace(['(', ')'], " ");
+        let mut parser = RuleParser {
+            words: text.split_whitespace().collect(),
+            index: 0,
+        };
+
+        let rule = parser.rule()?;
+        match parser.peek() {
+            "" => Ok(rule),
+            _ => Err(parser.error("the end of the rule")),
+        }
+    }
+}
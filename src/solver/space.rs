@@ -0,0 +1,330 @@
+//! The space of every pattern on the grid, for exhaustive searches.
+//!
+//! Each cell is empty or holds one of the 18 pieces, so a pattern is a number
+//! in base 19 with one digit per cell, row by row. The full 3x3 space has
+//! 19^9 (about 3.2 * 10^11) patterns, which is too many to scan one by one;
+//! restricting the pieces with an [`AttributeSet`] brings it down, e.g. red
+//! and blue small pieces only leave 5^9 (about two million).
+//!
+//! Counting the patterns a rule accepts does not need every pattern, see
+//! [`count_accepted`]: a rule tells at most four kinds of pieces apart, so
+//! even the full space takes at most 5^9 checks.
+
+use ratatui::style::Color;
+
+use crate::{
+    COLOR_BLUE, COLOR_RED, COLOR_YELLOW, HEIGHT, Pattern, Piece, PyramidType, SymbolSize, WIDTH,
+};
+
+use super::rule::Rule;
+
+const COLORS: [Color; 3] = [COLOR_RED, COLOR_BLUE, COLOR_YELLOW];
+const SIZES: [SymbolSize; 3] = [SymbolSize::SMALL, SymbolSize::MEDIUM, SymbolSize::LARGE];
+const TYPES: [PyramidType; 2] = [PyramidType::Straight, PyramidType::Angled];
+
+/// Number of different pieces.
+pub const PIECES: usize = COLORS.len() * SIZES.len() * TYPES.len();
+
+/// States of one cell: empty or any piece.
+const STATES: u64 = PIECES as u64 + 1;
+
+/// Number of cells on the grid.
+pub const CELLS: usize = WIDTH * HEIGHT;
+
+/// A pattern packed into an integer, see [`encode`].
+pub type Code = u64;
+
+/// The piece with the given index, in `0..PIECES`. Index `i` has type
+/// `i % 2`, color `i / 2 % 3` and size `i / 6`, in the order of the tables
+/// above.
+pub fn piece(index: usize) -> Piece {
+    Piece {
+        pyramid_type: TYPES[index % 2],
+        color: COLORS[index / 2 % 3],
+        size: SIZES[index / 6],
+        turned: false,
+    }
+}
+
+/// The inverse of [`piece`]. Unknown colors count as red.
+pub fn piece_index(pyramid: Piece) -> usize {
+    let position = |found: Option<usize>| found.unwrap_or(0);
+    let pyramid_type = position(TYPES.iter().position(|t| *t == pyramid.pyramid_type));
+    let color = position(COLORS.iter().position(|c| *c == pyramid.color));
+    let size = position(SIZES.iter().position(|s| *s == pyramid.size));
+    pyramid_type + 2 * color + 6 * size
+}
+
+/// Packs a pattern into a number, one base 19 digit per cell in row major
+/// order: 0 for an empty cell, `1 + piece_index` otherwise. Two patterns get
+/// the same code exactly when they have the same pieces in the same places,
+/// whatever order the pieces were placed in. Rules do not see whether an
+/// angled piece is turned, so neither does the code.
+pub fn encode(pattern: &Pattern) -> Code {
+    let mut digits = [0; CELLS];
+    for pyramid in pattern.iter() {
+        let (col, row) = pyramid.pos;
+        digits[row * WIDTH + col] = 1 + piece_index(pyramid.piece()) as u64;
+    }
+    pack(&digits)
+}
+
+fn pack(digits: &[u64; CELLS]) -> Code {
+    digits
+        .iter()
+        .rev()
+        .fold(0, |code, digit| code * STATES + digit)
+}
+
+/// The inverse of [`encode`]. Pieces come out in row major order.
+pub fn decode(mut code: Code) -> Pattern {
+    let mut pattern = Pattern::default();
+    for cell in 0..CELLS {
+        let digit = (code % STATES) as usize;
+        code /= STATES;
+        if digit > 0 {
+            pattern.set((cell % WIDTH, cell / WIDTH), piece(digit - 1));
+        }
+    }
+    pattern
+}
+
+/// Maps a `(col, row)` position to where it lands.
+pub type Symmetry = fn((usize, usize)) -> (usize, usize);
+
+/// The eight rotations and reflections of the square grid. The first one is
+/// the identity.
+pub const SYMMETRIES: [Symmetry; 8] = [
+    |(col, row)| (col, row),
+    |(col, row)| (WIDTH - 1 - row, col),
+    |(col, row)| (WIDTH - 1 - col, HEIGHT - 1 - row),
+    |(col, row)| (row, HEIGHT - 1 - col),
+    |(col, row)| (WIDTH - 1 - col, row),
+    |(col, row)| (col, HEIGHT - 1 - row),
+    |(col, row)| (row, col),
+    |(col, row)| (WIDTH - 1 - row, HEIGHT - 1 - col),
+];
+
+/// The smallest code among all rotations and reflections of the pattern, so
+/// patterns that are the same up to symmetry share it.
+///
+/// Only meaningful on a square grid, and a piece's own orientation is not
+/// turned with the grid.
+pub fn canonical(pattern: &Pattern) -> Code {
+    SYMMETRIES
+        .iter()
+        .map(|symmetry| {
+            let mut digits = [0; CELLS];
+            for pyramid in pattern.iter() {
+                let (col, row) = symmetry(pyramid.pos);
+                digits[row * WIDTH + col] = 1 + piece_index(pyramid.piece()) as u64;
+            }
+            pack(&digits)
+        })
+        .min()
+        .unwrap_or(0)
+}
+
+/// Which pieces may appear, as indices for [`piece`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeSet {
+    pieces: Vec<usize>,
+}
+
+impl Default for AttributeSet {
+    fn default() -> Self {
+        Self::all()
+    }
+}
+
+impl AttributeSet {
+    /// Every piece.
+    pub fn all() -> Self {
+        Self {
+            pieces: (0..PIECES).collect(),
+        }
+    }
+
+    /// Reads notation letters, e.g. `rbS^` for small straight red and blue
+    /// pieces. A kind without any letter is not restricted.
+    pub fn from_letters(letters: &str) -> Result<Self, char> {
+        let mut colors = vec![];
+        let mut sizes = vec![];
+        let mut types = vec![];
+        for letter in letters.chars() {
+            match letter {
+                'r' => colors.push(0),
+                'b' => colors.push(1),
+                'y' => colors.push(2),
+                'S' => sizes.push(0),
+                'M' => sizes.push(1),
+                'L' => sizes.push(2),
+                '^' => types.push(0),
+                '/' | 'a' => types.push(1),
+                other => return Err(other),
+            }
+        }
+
+        let pieces = (0..PIECES)
+            .filter(|index| {
+                let allows =
+                    |chosen: &[usize], value: usize| chosen.is_empty() || chosen.contains(&value);
+                allows(&types, index % 2)
+                    && allows(&colors, index / 2 % 3)
+                    && allows(&sizes, index / 6)
+            })
+            .collect();
+        Ok(Self { pieces })
+    }
+
+    pub fn pieces(&self) -> &[usize] {
+        &self.pieces
+    }
+
+    /// Number of patterns built from these pieces, `None` if it does not fit.
+    pub fn len(&self) -> Option<u64> {
+        (self.pieces.len() as u64 + 1).checked_pow(CELLS as u32)
+    }
+
+    /// Every pattern built from these pieces, starting with the empty one.
+    pub fn patterns(&self) -> Patterns<'_> {
+        Patterns {
+            pieces: &self.pieces,
+            digits: [0; CELLS],
+            done: false,
+        }
+    }
+}
+
+/// Iterator over a pattern space, counting through the cells like an
+/// odometer, see [`AttributeSet::patterns`].
+#[derive(Debug, Clone)]
+pub struct Patterns<'a> {
+    pieces: &'a [usize],
+    /// Per cell, 0 for empty or `1 + ` an index into `pieces`.
+    digits: [usize; CELLS],
+    done: bool,
+}
+
+impl Iterator for Patterns<'_> {
+    type Item = Pattern;
+
+    fn next(&mut self) -> Option<Pattern> {
+        if self.done {
+            return None;
+        }
+
+        let mut pattern = Pattern::default();
+        for (cell, digit) in self.digits.iter().enumerate() {
+            if *digit > 0 {
+                let pos = (cell % WIDTH, cell / WIDTH);
+                pattern.set(pos, piece(self.pieces[digit - 1]));
+            }
+        }
+
+        // Advance to the next pattern, carrying into later cells.
+        self.done = true;
+        for digit in self.digits.iter_mut() {
+            if *digit < self.pieces.len() {
+                *digit += 1;
+                self.done = false;
+                break;
+            }
+            *digit = 0;
+        }
+
+        Some(pattern)
+    }
+}
+
+/// Number of patterns built from `attributes` that `rule` accepts.
+///
+/// Pieces that match the same filters of the rule get the same verdict in
+/// every place, so each cell only runs through one piece of every such
+/// class, and an accepted pattern counts once for every way to swap its
+/// pieces for others of their class.
+pub fn count_accepted(rule: &Rule, attributes: &AttributeSet) -> u64 {
+    let filters = rule.filters();
+    let mut classes: Vec<(Vec<bool>, Piece, u64)> = vec![];
+    for &index in attributes.pieces() {
+        let piece = piece(index);
+        let matched: Vec<bool> = filters
+            .iter()
+            .map(|filter| filter.matches(&piece.at((0, 0))))
+            .collect();
+        match classes.iter_mut().find(|(class, _, _)| *class == matched) {
+            Some((_, _, members)) => *members += 1,
+            None => classes.push((matched, piece, 1)),
+        }
+    }
+    let classes: Vec<(Piece, u64)> = classes
+        .into_iter()
+        .map(|(_, piece, members)| (piece, members))
+        .collect();
+    count_cells(rule, &classes, &mut Pattern::default(), 0, 1)
+}
+
+/// Fills the cells from `cell` on with every class in turn, `weight` being
+/// the number of patterns the cells before stand for.
+fn count_cells(
+    rule: &Rule,
+    classes: &[(Piece, u64)],
+    pattern: &mut Pattern,
+    cell: usize,
+    weight: u64,
+) -> u64 {
+    if cell == CELLS {
+        return if rule.holds(pattern) { weight } else { 0 };
+    }
+    let pos = (cell % WIDTH, cell / WIDTH);
+    pattern.clear(pos);
+    let mut accepted = count_cells(rule, classes, pattern, cell + 1, weight);
+    for (piece, members) in classes {
+        pattern.set(pos, *piece);
+        accepted += count_cells(rule, classes, pattern, cell + 1, weight * members);
+    }
+    pattern.clear(pos);
+    accepted
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RULES: &[&str] = &[
+        "every red piece is large",
+        "at least 2 angled pieces",
+        "a red piece touches a blue piece",
+        "no small piece touches a blue piece",
+        "a red piece is in the top row",
+        "no angled piece is in a corner",
+        "more red pieces than blue pieces",
+        "not (exactly 3 straight pieces)",
+    ];
+
+    #[test]
+    fn counts_like_a_scan() {
+        for letters in ["rb^M", "rS", "yL/"] {
+            let attributes = AttributeSet::from_letters(letters).unwrap();
+            for text in RULES {
+                let rule: Rule = text.parse().unwrap();
+                let scanned = attributes
+                    .patterns()
+                    .filter(|pattern| rule.holds(pattern))
+                    .count() as u64;
+                assert_eq!(
+                    count_accepted(&rule, &attributes),
+                    scanned,
+                    "{letters} {rule}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn counts_the_full_space() {
+        let rule: Rule = "there is no red piece".parse().unwrap();
+        // a cell may be empty or hold any of the twelve other pieces
+        assert_eq!(count_accepted(&rule, &AttributeSet::all()), 13u64.pow(9));
+    }
+}
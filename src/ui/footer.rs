@@ -1,4 +1,8 @@
-use crate::{App, COLOR_INACTIVE, SymbolSize};
+use crate::{
+    App, COLOR_INACTIVE, GlyphSet, Keymap, Piece, PyramidType, SymbolSize,
+    game::{Game, Phase},
+    helpers::gallery::{Filter, shown_patterns},
+};
 use ratatui::{
     Frame,
     layout::{Alignment, Constraint, Direction, Layout, Rect},
@@ -9,8 +13,17 @@ use ratatui::{
 
 const SELECTED_STRING: &str = "selected";
 
-pub fn render_footer(app: &App, frame: &mut Frame, area: Rect, extra: String) {
-    let mode = app.mode.to_string();
+/// The footer only has room for the newest few messages, the log has all.
+const SHOWN_NOTIFICATIONS: usize = 3;
+
+pub fn render_footer(app: &App, frame: &mut Frame, area: Rect) {
+    let moving = match (app.selection, app.carried, app.stamp) {
+        (Some(_), _, _) => " select",
+        (None, Some(_), _) => " carry",
+        (None, None, true) => " stamp",
+        (None, None, false) => "",
+    };
+    let mode = format!("{}{moving} {}", app.screen(), app.vim.pending());
     let x = app.current_pos.0;
     let y = app.current_pos.1;
     let pyramid_nbr = app.active_pattern().len();
@@ -18,32 +31,91 @@ pub fn render_footer(app: &App, frame: &mut Frame, area: Rect, extra: String) {
         .direction(Direction::Vertical)
         .constraints([Constraint::Length(1), Constraint::Length(2)])
         .split(area);
+    let tokens = match &app.game {
+        Some(Game {
+            phase: Phase::Over { winner, rule },
+            seats,
+            ..
+        }) => format!("\t {} won: {rule}", seats[*winner].name),
+        Some(game) => {
+            let tokens = game
+                .seats
+                .iter()
+                .enumerate()
+                .filter(|(seat, _)| *seat != game.master)
+                .map(|(_, seat)| format!("{} {}", seat.name, seat.tokens))
+                .collect::<Vec<_>>()
+                .join(", ");
+            format!("\t tokens: {tokens}")
+        }
+        None => String::new(),
+    };
+    let help_key = match app.keymap {
+        Keymap::Default => "h",
+        Keymap::Vim => ":help",
+    };
     let help_paragraph = Paragraph::new(format!(
-        "pos:{x},{y}, nbr:{pyramid_nbr}\t Press h to show/hide help menu\t  MODE>{mode}"
+        "pos:{x},{y}, nbr:{pyramid_nbr}\t Press {help_key} to show/hide help menu\t  MODE>{mode}{tokens}"
     ));
     frame.render_widget(help_paragraph, areas[0]);
+    let note = app
+        .patterns
+        .get(app.selected_pattern_index)
+        .map(|pattern| pattern.annotation.note.as_str())
+        .filter(|note| !note.is_empty());
+    if let Some(note) = note {
+        let width = (note.chars().count() + 8) as u16;
+        let note_area = Rect {
+            x: areas[0].right().saturating_sub(width),
+            width: width.min(areas[0].width),
+            ..areas[0]
+        };
+        frame.render_widget(
+            Paragraph::new(format!("note: {note}"))
+                .alignment(Alignment::Right)
+                .italic(),
+            note_area,
+        );
+    }
 
+    let shown = shown_patterns(app);
     let mut bla = app
         .patterns
         .iter()
         .enumerate()
         .map(|(index, _pat)| {
-            if index >= app.render_start_index && index <= app.render_end_index {
-                if index == app.selected_pattern_index {
-                    format!(" {index} ").red().underlined()
-                } else {
-                    format!(" {index} ").red()
+            let slot = shown.iter().position(|shown| *shown == index);
+            match slot {
+                Some(slot) if (app.render_start_index..=app.render_end_index).contains(&slot) => {
+                    if index == app.selected_pattern_index {
+                        format!(" {index} ").red().underlined()
+                    } else {
+                        format!(" {index} ").red()
+                    }
                 }
-            } else {
-                format!(" {index} ").white()
+                Some(_) => format!(" {index} ").white(),
+                None => format!(" {index} ").dark_gray(),
             }
         })
         .collect::<Vec<Span>>();
 
     let selected_pattern_index = app.selected_pattern_index;
+    let page_size = app.patterns_per_row * app.pattern_rows;
+    let page = app.render_start_index.div_ceil(page_size) + 1;
+    let pages = shown.len().div_ceil(page_size).max(1);
 
-    bla.push(format!("{extra}").red().underlined());
-    bla.push(format!(" ({selected_pattern_index})").underlined());
+    let filter = match &app.filter {
+        Filter::All => String::new(),
+        filter => format!(", {filter}"),
+    };
+    bla.push(format!(" ({selected_pattern_index}, page {page}/{pages}{filter})").underlined());
+    let current: Vec<_> = app.notifications.current().collect();
+    for notification in &current[current.len().saturating_sub(SHOWN_NOTIFICATIONS)..] {
+        bla.push(Span::styled(
+            format!("  {}", notification.message),
+            notification.severity.color(),
+        ));
+    }
     let pattern_tracker = Paragraph::new(Line::from(bla));
 
     frame.render_widget(pattern_tracker, areas[1]);
@@ -56,28 +128,77 @@ pub fn render_top_down_pyramid(
     color: Color,
     selected_symbol: bool,
 ) {
-    let mut lines = vec![];
+    // Consider writing triangles with these fullheight triangle characters from JuliaFont. Also consider using .bg(color)
+    let lines = top_down_pyramid_lines(height);
+    render_pyramid_lines(frame, area, lines, color, selected_symbol);
+}
+
+pub fn render_top_down_pyramid_angled(
+    frame: &mut Frame,
+    area: Rect,
+    height: SymbolSize,
+    turned: bool,
+    color: Color,
+    selected_symbol: bool,
+) {
+    let lines = top_down_pyramid_angled_lines(height, turned);
+    render_pyramid_lines(frame, area, lines, color, selected_symbol);
+}
 
-    let pyramid_height = match height {
+fn render_pyramid_lines(
+    frame: &mut Frame,
+    area: Rect,
+    lines: Vec<String>,
+    color: Color,
+    selected_symbol: bool,
+) {
+    let lines = lines
+        .into_iter()
+        .map(|line| Line::from(Span::styled(line, Style::default().fg(color))))
+        .collect::<Vec<Line>>();
+
+    let border_title = if selected_symbol { SELECTED_STRING } else { "" };
+
+    let paragraph = Paragraph::new(lines)
+        .alignment(ratatui::layout::Alignment::Center)
+        .block(
+            Block::new()
+                .borders(Borders::ALL)
+                .title_bottom(border_title)
+                .title_alignment(Alignment::Center)
+                .border_style(if selected_symbol {
+                    color
+                } else {
+                    COLOR_INACTIVE
+                }),
+        );
+    frame.render_widget(paragraph, area);
+}
+
+fn pyramid_height(height: SymbolSize) -> usize {
+    match height {
         SymbolSize::SMALL => 4,
         SymbolSize::MEDIUM => 6,
         SymbolSize::LARGE => 8,
-    };
+    }
+}
 
-    let empty_line_nbr = match height {
+fn empty_line_nbr(height: SymbolSize) -> usize {
+    match height {
         SymbolSize::SMALL => 2,
         SymbolSize::MEDIUM => 1,
         SymbolSize::LARGE => 0,
-    };
+    }
+}
 
-    for _ in 0..empty_line_nbr {
-        let empty = " ".repeat(1);
+/// The lines of a straight pyramid seen from above, `█` for the solid faces
+/// and `▒` for the shaded ones.
+pub fn top_down_pyramid_lines(height: SymbolSize) -> Vec<String> {
+    let mut lines = vec![];
+    let pyramid_height = pyramid_height(height);
 
-        lines.push(Line::from(Span::styled(
-            // "█".repeat(count_alt).push_str("▒".repeat(2)),
-            format!("{empty}"),
-            Style::default().fg(color),
-        )));
+    for _ in 0..empty_line_nbr(height) {
+        lines.push(" ".to_string());
     }
 
     for i in 0..(pyramid_height / 2) {
@@ -86,10 +207,7 @@ pub fn render_top_down_pyramid(
         let side = "█".repeat(count_alt);
         let side_alt = "▒".repeat(count);
 
-        lines.push(Line::from(Span::styled(
-            format!("{side}{side_alt}{side_alt}{side}"),
-            Style::default().fg(color),
-        )));
+        lines.push(format!("{side}{side_alt}{side_alt}{side}"));
     }
 
     for i in 1..(pyramid_height / 2) {
@@ -98,107 +216,48 @@ pub fn render_top_down_pyramid(
         let side = "█".repeat(count_alt);
         let side_alt = "▒".repeat(count);
 
-        lines.push(Line::from(Span::styled(
-            // "█".repeat(count_alt).push_str("▒".repeat(2)),
-            format!("{side}{side_alt}{side_alt}{side}"),
-            Style::default().fg(color),
-        )));
+        lines.push(format!("{side}{side_alt}{side_alt}{side}"));
     }
 
-    // Consider writing triangles with these fullheight triangle characters from JuliaFont. Also consider using .bg(color)
-
-    let border_title = if selected_symbol { SELECTED_STRING } else { "" };
-
-    let paragraph = Paragraph::new(lines)
-        .alignment(ratatui::layout::Alignment::Center)
-        .block(
-            Block::new()
-                .borders(Borders::ALL)
-                .title_bottom(border_title)
-                .title_alignment(Alignment::Center)
-                .border_style(if selected_symbol {
-                    color
-                } else {
-                    COLOR_INACTIVE
-                }),
-        );
-    frame.render_widget(paragraph, area);
+    lines
 }
 
-pub fn render_top_down_pyramid_angled(
-    frame: &mut Frame,
-    area: Rect,
-    height: SymbolSize,
-    color: Color,
-    selected_symbol: bool,
-) {
-    let mut lines = vec![];
-    let pyramid_height = match height {
-        SymbolSize::SMALL => 4,
-        SymbolSize::MEDIUM => 6,
-        SymbolSize::LARGE => 8,
+/// The lines of an angled pyramid seen from above, solid top left and bottom
+/// right, or top right and bottom left when `turned`.
+pub fn top_down_pyramid_angled_lines(height: SymbolSize, turned: bool) -> Vec<String> {
+    let (solid, shaded) = if turned {
+        ("▒", "█")
+    } else {
+        ("█", "▒")
     };
+    let mut lines = vec![];
+    let pyramid_height = pyramid_height(height);
 
-    let empty_line_nbr = match height {
-        SymbolSize::SMALL => 2,
-        SymbolSize::MEDIUM => 1,
-        SymbolSize::LARGE => 0,
-    };
-
-    for _ in 0..empty_line_nbr {
-        let empty = " ".repeat(1);
-
-        lines.push(Line::from(Span::styled(
-            // "█".repeat(count_alt).push_str("▒".repeat(2)),
-            format!("{empty}"),
-            Style::default().fg(color),
-        )));
+    for _ in 0..empty_line_nbr(height) {
+        lines.push(" ".to_string());
     }
 
     for i in 0..(pyramid_height / 2) {
         let count_alt = 1 + i * 2;
         let empty_count = pyramid_height - 1 - i * 2;
-        let side = "█".repeat(count_alt);
+        let side = solid.repeat(count_alt);
         let empty = " ".repeat(empty_count);
-        let side_alt = "▒".repeat(count_alt);
+        let side_alt = shaded.repeat(count_alt);
 
-        lines.push(Line::from(Span::styled(
-            // "█".repeat(count_alt).push_str("▒".repeat(2)),
-            format!("{empty}{side}{side_alt}{empty}"),
-            Style::default().fg(color),
-        )));
+        lines.push(format!("{empty}{side}{side_alt}{empty}"));
     }
 
     for i in 0..(pyramid_height / 2) {
         let count_alt = pyramid_height - 1 - i * 2;
         let empty_count = 1 + i * 2;
-        let side = "█".repeat(count_alt);
+        let side = solid.repeat(count_alt);
         let empty = " ".repeat(empty_count);
-        let side_alt = "▒".repeat(count_alt);
+        let side_alt = shaded.repeat(count_alt);
 
-        lines.push(Line::from(Span::styled(
-            // "█".repeat(count_alt).push_str("▒".repeat(2)),
-            format!("{empty}{side_alt}{side}{empty}"),
-            Style::default().fg(color),
-        )));
+        lines.push(format!("{empty}{side_alt}{side}{empty}"));
     }
 
-    let border_title = if selected_symbol { SELECTED_STRING } else { "" };
-
-    let paragraph = Paragraph::new(lines)
-        .alignment(ratatui::layout::Alignment::Center)
-        .block(
-            Block::new()
-                .borders(Borders::ALL)
-                .title_bottom(border_title)
-                .title_alignment(Alignment::Center)
-                .border_style(if selected_symbol {
-                    color
-                } else {
-                    COLOR_INACTIVE
-                }),
-        );
-    frame.render_widget(paragraph, area);
+    lines
 }
 
 pub fn render_empty(frame: &mut Frame, area: Rect, color: Color, selected_symbol: bool) {
@@ -219,3 +278,95 @@ pub fn render_empty(frame: &mut Frame, area: Rect, color: Color, selected_symbol
         );
     frame.render_widget(paragraph, area);
 }
+
+/// Glyph rows for the compact renderer, indexed by size. Small pieces take a
+/// 2x2 character footprint, medium 3x3 and large 4x4.
+const COMPACT_STRAIGHT: [&[&str]; 3] = [
+    &["◣◢", "◤◥"],
+    &["◣▒◢", "█╳█", "◤▒◥"],
+    &["◣▒▒◢", "█◣◢█", "█◤◥█", "◤▒▒◥"],
+];
+
+// Angled pieces are solid top left and bottom right, and the other way
+// round when turned, like `top_down_pyramid_angled_lines`.
+const COMPACT_ANGLED: [&[&str]; 3] = [
+    &["◢◺", "◹◤"],
+    &["🭇█🬼", "█╲█", "🭢█🭗"],
+    &[" 🭇🬼 ", "🭊█▒🬿", "🭥▒█🭚", " 🭢🭗 "],
+];
+
+const COMPACT_ANGLED_TURNED: [&[&str]; 3] = [
+    &["◿◣", "◥◸"],
+    &["🭇█🬼", "█╱█", "🭢█🭗"],
+    &[" 🭇🬼 ", "🭊▒█🬿", "🭥█▒🭚", " 🭢🭗 "],
+];
+
+// Fallback for fonts without the geometric shapes / legacy computing blocks.
+const COMPACT_STRAIGHT_ASCII: [&[&str]; 3] = [
+    &["\\/", "/\\"],
+    &["\\:/", "#X#", "/:\\"],
+    &["\\::/", "#\\/#", "#/\\#", "/::\\"],
+];
+
+const COMPACT_ANGLED_ASCII: [&[&str]; 3] = [
+    &["#\\", "\\#"],
+    &["/^\\", "<\\>", "\\v/"],
+    &[" /\\ ", "/#:\\", "\\:#/", " \\/ "],
+];
+
+const COMPACT_ANGLED_TURNED_ASCII: [&[&str]; 3] = [
+    &["/#", "#/"],
+    &["/^\\", "</>", "\\v/"],
+    &[" /\\ ", "/:#\\", "\\#:/", " \\/ "],
+];
+
+/// Width and height of the area reserved for a single compact cell.
+pub const COMPACT_CELL_WIDTH: u16 = 5;
+pub const COMPACT_CELL_HEIGHT: u16 = 4;
+
+pub fn compact_pyramid_lines(piece: Piece, glyphs: GlyphSet) -> &'static [&'static str] {
+    let size_index = match piece.size {
+        SymbolSize::SMALL => 0,
+        SymbolSize::MEDIUM => 1,
+        SymbolSize::LARGE => 2,
+    };
+
+    let table = match (piece.pyramid_type, piece.turned, glyphs) {
+        (PyramidType::Straight, _, GlyphSet::Unicode) => COMPACT_STRAIGHT,
+        (PyramidType::Angled, false, GlyphSet::Unicode) => COMPACT_ANGLED,
+        (PyramidType::Angled, true, GlyphSet::Unicode) => COMPACT_ANGLED_TURNED,
+        (PyramidType::Straight, _, GlyphSet::Ascii) => COMPACT_STRAIGHT_ASCII,
+        (PyramidType::Angled, false, GlyphSet::Ascii) => COMPACT_ANGLED_ASCII,
+        (PyramidType::Angled, true, GlyphSet::Ascii) => COMPACT_ANGLED_TURNED_ASCII,
+    };
+    table[size_index]
+}
+
+pub fn render_compact_pyramid(
+    frame: &mut Frame,
+    area: Rect,
+    pyramid: Option<Piece>,
+    color: Color,
+    glyphs: GlyphSet,
+    selected_symbol: bool,
+) {
+    let style = if selected_symbol {
+        Style::default().fg(color).bg(COLOR_INACTIVE)
+    } else {
+        Style::default().fg(color)
+    };
+
+    let rows: &[&str] = match pyramid {
+        Some(piece) => compact_pyramid_lines(piece, glyphs),
+        None if selected_symbol => &["  "],
+        None => &[],
+    };
+
+    // Center the glyph block vertically inside the cell.
+    let top_padding = (COMPACT_CELL_HEIGHT as usize).saturating_sub(rows.len()) / 2;
+    let mut lines = vec![Line::from(""); top_padding];
+    lines.extend(rows.iter().map(|row| Line::from(Span::styled(*row, style))));
+
+    let paragraph = Paragraph::new(lines).alignment(Alignment::Center);
+    frame.render_widget(paragraph, area);
+}
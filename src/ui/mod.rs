@@ -1 +1,3 @@
 pub mod footer;
+pub mod palette;
+pub mod text_input;
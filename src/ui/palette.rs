@@ -0,0 +1,118 @@
+use ratatui::{
+    Frame,
+    layout::{Position, Rect},
+    style::{Color, Style, Stylize},
+    text::Line,
+    widgets::{Block, Clear, Paragraph},
+};
+
+use crate::{App, COLOR_BLUE, COLOR_RED, COLOR_YELLOW, Piece, PyramidType, SymbolSize, popup_area};
+
+/// One thing about a piece the palette sets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Attribute {
+    Color(Color),
+    Size(SymbolSize),
+    Type(PyramidType),
+}
+
+impl Attribute {
+    pub fn apply(self, piece: &mut Piece) {
+        match self {
+            Attribute::Color(color) => piece.color = color,
+            Attribute::Size(size) => piece.size = size,
+            Attribute::Type(pyramid_type) if piece.pyramid_type != pyramid_type => {
+                piece.pyramid_type = pyramid_type;
+                piece.turned = false;
+            }
+            Attribute::Type(_) => {}
+        }
+    }
+
+    fn of(self, piece: Piece) -> bool {
+        match self {
+            Attribute::Color(color) => piece.color == color,
+            Attribute::Size(size) => piece.size == size,
+            Attribute::Type(pyramid_type) => piece.pyramid_type == pyramid_type,
+        }
+    }
+
+    pub fn parse(name: &str) -> Option<Self> {
+        PALETTE
+            .iter()
+            .find(|(_, entry, _)| *entry == name)
+            .map(|(_, _, attribute)| *attribute)
+    }
+}
+
+/// The palette rows: the key that picks an attribute, its name, and the
+/// attribute.
+pub const PALETTE: [(char, &str, Attribute); 8] = [
+    ('1', "red", Attribute::Color(COLOR_RED)),
+    ('2', "blue", Attribute::Color(COLOR_BLUE)),
+    ('3', "yellow", Attribute::Color(COLOR_YELLOW)),
+    ('4', "small", Attribute::Size(SymbolSize::SMALL)),
+    ('5', "medium", Attribute::Size(SymbolSize::MEDIUM)),
+    ('6', "large", Attribute::Size(SymbolSize::LARGE)),
+    ('7', "straight", Attribute::Type(PyramidType::Straight)),
+    ('8', "angled", Attribute::Type(PyramidType::Angled)),
+];
+
+/// What a click on the palette does.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteEntry {
+    Attribute(Attribute),
+    Stamp,
+}
+
+const PALETTE_WIDTH: u16 = 16;
+
+/// Right of the editor popup, or over its right edge on narrow terminals.
+fn palette_area(area: Rect) -> Rect {
+    let editor = popup_area(area, 34);
+    let height = PALETTE.len() as u16 + 3;
+    let x = editor
+        .right()
+        .min(area.right().saturating_sub(PALETTE_WIDTH));
+    Rect::new(x, editor.y, PALETTE_WIDTH, height).intersection(area)
+}
+
+pub fn render_palette(app: &App, frame: &mut Frame) {
+    let area = palette_area(frame.area());
+    let mut lines: Vec<Line> = PALETTE
+        .iter()
+        .map(|(key, name, attribute)| {
+            let style = match attribute {
+                Attribute::Color(color) => Style::new().fg(app.piece_color(*color, false)),
+                _ => Style::new(),
+            };
+            let line = Line::styled(format!("{key} {name}"), style);
+            match attribute.of(app.brush) {
+                true => line.reversed(),
+                false => line,
+            }
+        })
+        .collect();
+    let stamp = if app.stamp { "on" } else { "off" };
+    lines.push(Line::from(format!("f stamp {stamp}")));
+
+    frame.render_widget(Clear, area);
+    frame.render_widget(
+        Paragraph::new(lines).block(Block::bordered().title("Palette")),
+        area,
+    );
+}
+
+/// The palette row at a terminal position, for mouse clicks.
+pub fn palette_entry_at(app: &App, column: u16, row: u16) -> Option<PaletteEntry> {
+    let area = palette_area(app.frame_area);
+    if !area.contains(Position::new(column, row)) {
+        return None;
+    }
+    let index = row.checked_sub(area.y + 1)? as usize;
+    match PALETTE.get(index) {
+        Some((_, _, attribute)) => Some(PaletteEntry::Attribute(*attribute)),
+        None if index == PALETTE.len() => Some(PaletteEntry::Stamp),
+        None => None,
+    }
+}
@@ -0,0 +1,231 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use ratatui::{
+    Frame,
+    layout::Rect,
+    style::{Color, Style, Stylize},
+    text::{Line, Span},
+    widgets::Paragraph,
+};
+
+/// Checks the text before it is submitted, returning why it is not accepted.
+pub type Validator = fn(&str) -> Result<(), String>;
+
+/// The whole texts the typed text could be completed to.
+pub type Completer = fn(&str) -> Vec<String>;
+
+/// What a key did to a text input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum InputEvent {
+    Edited,
+    /// Enter on text the validator accepts.
+    Submitted(String),
+    Cancelled,
+    /// The key is not for the input, e.g. Tab to move between fields.
+    Ignored,
+}
+
+/// A single line of editable text with a cursor, history and validation.
+#[derive(Debug, Clone, Default)]
+pub struct TextInput {
+    text: String,
+    /// Cursor position in characters, `0..=len`.
+    cursor: usize,
+    history: Vec<String>,
+    /// The history entry shown, counted from the newest.
+    history_index: Option<usize>,
+    /// What was typed before browsing the history.
+    draft: String,
+    validator: Option<Validator>,
+    /// Why the last submit was refused.
+    error: Option<String>,
+    completer: Option<Completer>,
+    /// The completions Tab found when it could not pick one.
+    hint: Option<String>,
+}
+
+impl TextInput {
+    pub fn new(text: &str) -> Self {
+        Self {
+            text: text.to_string(),
+            cursor: text.chars().count(),
+            ..Self::default()
+        }
+    }
+
+    pub fn with_history(mut self, history: Vec<String>) -> Self {
+        self.history = history;
+        self
+    }
+
+    pub fn with_validator(mut self, validator: Validator) -> Self {
+        self.validator = Some(validator);
+        self
+    }
+
+    pub fn with_completer(mut self, completer: Completer) -> Self {
+        self.completer = Some(completer);
+        self
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// Earlier submissions, oldest first, to hand to the next input.
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Inserts text at the cursor. Line breaks become spaces, the input is
+    /// a single line.
+    pub fn paste(&mut self, text: &str) {
+        for c in text.chars() {
+            self.insert(if c == '\n' || c == '\r' { ' ' } else { c });
+        }
+    }
+
+    pub fn handle_key(&mut self, key: KeyEvent) -> InputEvent {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Esc => return InputEvent::Cancelled,
+            KeyCode::Enter => return self.submit(),
+            KeyCode::Tab if self.completer.is_some() => {
+                self.complete();
+                return InputEvent::Edited;
+            }
+            KeyCode::Char('a') if ctrl => self.cursor = 0,
+            KeyCode::Char('e') if ctrl => self.cursor = self.len(),
+            KeyCode::Char('u') if ctrl => {
+                self.text = self.text.chars().skip(self.cursor).collect();
+                self.cursor = 0;
+            }
+            KeyCode::Char(c) if !ctrl => self.insert(c),
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.remove(self.cursor);
+            }
+            KeyCode::Delete if self.cursor < self.len() => self.remove(self.cursor),
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.len()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.len(),
+            KeyCode::Up if !self.history.is_empty() => {
+                let index = self.history_index.map_or(0, |index| index + 1);
+                self.show_history(Some(index.min(self.history.len() - 1)));
+            }
+            KeyCode::Down if self.history_index.is_some() => {
+                self.show_history(self.history_index.and_then(|index| index.checked_sub(1)));
+            }
+            KeyCode::Backspace | KeyCode::Delete => {}
+            _ => return InputEvent::Ignored,
+        }
+        self.error = None;
+        self.hint = None;
+        InputEvent::Edited
+    }
+
+    /// Draws the text with the cursor, and the validation error if there is
+    /// one, on the line below.
+    pub fn render(&self, frame: &mut Frame, area: Rect, label: &str, focused: bool) {
+        let before: String = self.text.chars().take(self.cursor).collect();
+        let at: String = self.text.chars().skip(self.cursor).take(1).collect();
+        let after: String = self.text.chars().skip(self.cursor + 1).collect();
+
+        let mut spans = vec![Span::raw(format!("{label}{before}"))];
+        if focused {
+            let at = if at.is_empty() { " ".to_string() } else { at };
+            spans.push(Span::styled(at, Style::new().reversed()));
+        } else {
+            spans.push(Span::raw(at));
+        }
+        spans.push(Span::raw(after));
+
+        let mut lines = vec![Line::from(spans)];
+        if let Some(error) = &self.error {
+            lines.push(Line::from(error.as_str()).fg(Color::Red));
+        } else if let Some(hint) = &self.hint {
+            lines.push(Line::from(hint.as_str()).fg(Color::DarkGray));
+        }
+        frame.render_widget(Paragraph::new(lines), area);
+    }
+
+    fn len(&self) -> usize {
+        self.text.chars().count()
+    }
+
+    fn byte_index(&self, cursor: usize) -> usize {
+        self.text
+            .char_indices()
+            .nth(cursor)
+            .map_or(self.text.len(), |(index, _)| index)
+    }
+
+    fn insert(&mut self, c: char) {
+        let index = self.byte_index(self.cursor);
+        self.text.insert(index, c);
+        self.cursor += 1;
+    }
+
+    fn remove(&mut self, cursor: usize) {
+        let index = self.byte_index(cursor);
+        self.text.remove(index);
+    }
+
+    fn show_history(&mut self, index: Option<usize>) {
+        if self.history_index.is_none() {
+            self.draft = self.text.clone();
+        }
+        self.history_index = index;
+        self.text = match index {
+            Some(index) => self.history[self.history.len() - 1 - index].clone(),
+            None => self.draft.clone(),
+        };
+        self.cursor = self.len();
+    }
+
+    /// Completes the text as far as all completions agree, or with a space
+    /// after it when there is only one.
+    fn complete(&mut self) {
+        let Some(completer) = self.completer else {
+            return;
+        };
+        let completions = completer(&self.text);
+        self.hint = None;
+        match completions.as_slice() {
+            [] => {}
+            [completion] => self.text = format!("{completion} "),
+            [first, rest @ ..] => {
+                let common = rest.iter().fold(first.as_str(), |common, completion| {
+                    let length = common
+                        .char_indices()
+                        .zip(completion.chars())
+                        .take_while(|((_, a), b)| a == b)
+                        .last()
+                        .map_or(0, |((index, a), _)| index + a.len_utf8());
+                    &common[..length]
+                });
+                self.text = common.to_string();
+                let words: Vec<_> = completions
+                    .iter()
+                    .map(|completion| completion.rsplit(' ').next().unwrap_or(completion))
+                    .collect();
+                self.hint = Some(words.join("  "));
+            }
+        }
+        self.cursor = self.len();
+    }
+
+    fn submit(&mut self) -> InputEvent {
+        if let Some(validator) = self.validator
+            && let Err(err) = validator(&self.text)
+        {
+            self.error = Some(err);
+            return InputEvent::Edited;
+        }
+        if !self.text.trim().is_empty() && self.history.last() != Some(&self.text) {
+            self.history.push(self.text.clone());
+        }
+        self.history_index = None;
+        InputEvent::Submitted(self.text.clone())
+    }
+}